@@ -0,0 +1,61 @@
+//! Minimal `command [subcommand] --key value` parsing.
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use near_sdk::json_types::ValidAccountId;
+
+pub struct Args {
+    pub commands: Vec<String>,
+    options: HashMap<String, String>,
+}
+
+impl Args {
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut commands = vec![];
+        let mut options = HashMap::new();
+        let mut iter = args.into_iter();
+        while let Some(arg) = iter.next() {
+            if let Some(key) = arg.strip_prefix("--") {
+                let value = iter.next().ok_or(format!("missing value of --{}", key))?;
+                options.insert(key.to_string(), value);
+            } else {
+                commands.push(arg);
+            }
+        }
+        Ok(Args { commands, options })
+    }
+
+    pub fn command(&self, index: usize) -> Option<&str> {
+        self.commands.get(index).map(|command| command.as_str())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.options.get(key).map(|value| value.as_str())
+    }
+
+    pub fn required(&self, key: &str) -> Result<&str, String> {
+        self.get(key).ok_or(format!("missing --{}", key))
+    }
+
+    pub fn value<T: FromStr>(&self, key: &str) -> Result<T, String> {
+        self.required(key)?
+            .parse()
+            .map_err(|_| format!("invalid --{}", key))
+    }
+
+    pub fn value_or<T: FromStr>(&self, key: &str, default: T) -> Result<T, String> {
+        match self.get(key) {
+            Some(_) => self.value(key),
+            None => Ok(default),
+        }
+    }
+
+    pub fn account(&self, key: &str) -> Result<ValidAccountId, String> {
+        to_account(self.required(key)?)
+    }
+}
+
+pub fn to_account(account_id: &str) -> Result<ValidAccountId, String> {
+    ValidAccountId::try_from(account_id).map_err(|_| format!("invalid account id {}", account_id))
+}
@@ -0,0 +1,149 @@
+//! Offline helper for vault operators: builds call payloads, plain or as
+//! multisig requests, and reports from saved view output.
+use std::convert::TryFrom;
+use std::fs;
+
+use near_sdk::json_types::Base58CryptoHash;
+use near_sdk::serde::de::DeserializeOwned;
+use near_sdk::serde_json;
+use near_sdk::CryptoHash;
+use sha2::{Digest, Sha256};
+use vault_client::{
+    AccountOutput, AddAccountRequest, NewRequest, StateSnapshot, Stats, VaultClient,
+    ADD_ACCOUNT_DEPOSIT,
+};
+
+pub mod args;
+pub mod payload;
+pub mod payroll;
+pub mod report;
+pub mod snapshot;
+
+use args::Args;
+use payload::Action;
+
+pub const USAGE: &str = "usage: vault-cli <command> [--multisig <account_id>]
+  new --vault <id> --owner <id> --token <id> --total <amount> --start <ts> --interval <sec> --rounds <n>
+  add-account --vault <id> --account <id> --start <ts> (--interval <sec> | --day <1-31>) --rounds <n> --per-round <amount> [--deposit <yocto>]
+  payment --vault <id> --receiver <id> --amount <amount>
+  set-owner --vault <id> --owner <id>
+  stage-code --vault <id> --wasm <path>
+  deploy-staged --vault <id> (--hash <base58> | --wasm <path>)
+  batch --vault <id> --csv <path>
+  report vesting --accounts <json> --at <ts>
+  report solvency --stats <json> --accounts <json> --from <ts> --to <ts> --step <sec>
+  verify --snapshot <json>[,<json>...] --at <ts>";
+
+/// Runs a command and returns what to print.
+pub fn run(args: &Args) -> Result<String, String> {
+    match args.command(0) {
+        Some("report") => report(args),
+        Some("verify") => verify(args),
+        Some(command) => {
+            let vault_id = args.account("vault")?;
+            let actions = actions(command, &VaultClient::new(vault_id.to_string()), args)?;
+            let output = match args.get("multisig") {
+                Some(_) => {
+                    args.account("multisig")?;
+                    payload::to_multisig_request(vault_id.as_ref(), &actions)
+                }
+                None => payload::to_calls(vault_id.as_ref(), &actions),
+            };
+            serde_json::to_string_pretty(&output).map_err(|err| err.to_string())
+        }
+        None => Err(USAGE.to_string()),
+    }
+}
+
+fn actions(command: &str, client: &VaultClient, args: &Args) -> Result<Vec<Action>, String> {
+    let call = match command {
+        "new" => NewRequest::new(args.account("owner")?, args.account("token")?, args.value("total")?)
+            .start_timestamp(args.value("start")?)
+            .release_interval(args.value("interval")?)
+            .release_rounds(args.value("rounds")?)
+            .build(),
+        "add-account" => {
+            let request = AddAccountRequest::new(args.account("account")?, args.value("per-round")?)
+                .start_timestamp(args.value("start")?)
+                .release_rounds(args.value("rounds")?)
+                .deposit(args.value_or("deposit", ADD_ACCOUNT_DEPOSIT)?);
+            match args.get("day") {
+                Some(_) => request.release_day(args.value("day")?),
+                None => request.release_interval(args.value("interval")?),
+            }
+            .build()
+        }
+        "payment" => client.payment(args.account("receiver")?, args.value("amount")?),
+        "set-owner" => client.set_owner(args.account("owner")?),
+        "stage-code" => return Ok(vec![payload::stage_code(read_code(args)?)]),
+        "deploy-staged" => {
+            let expected_hash = match args.get("hash") {
+                Some(hash) => Base58CryptoHash::try_from(hash).map_err(|_| "invalid --hash")?,
+                None => code_hash(&read_code(args)?),
+            };
+            client.deploy_staged_code(expected_hash)
+        }
+        "batch" => {
+            let entries = payroll::parse_payroll(&read(args.required("csv")?)?)?;
+            return entries
+                .iter()
+                .map(|entry| entry.to_call().map(Action::from))
+                .collect();
+        }
+        _ => return Err(format!("unknown command {}\n{}", command, USAGE)),
+    };
+    Ok(vec![call.into()])
+}
+
+fn report(args: &Args) -> Result<String, String> {
+    let accounts: Vec<AccountOutput> = read_json(args.required("accounts")?)?;
+    match args.command(1) {
+        Some("vesting") => Ok(report::render_vesting(&report::vesting_table(
+            &accounts,
+            args.value("at")?,
+        )?)),
+        Some("solvency") => {
+            let stats: Stats = read_json(args.required("stats")?)?;
+            let points = report::solvency(
+                &stats,
+                &accounts,
+                args.value("from")?,
+                args.value("to")?,
+                args.value("step")?,
+            )?;
+            Ok(report::render_solvency(&points))
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+/// Checks pages of `export_state` and prints `Stats` recomputed from them.
+fn verify(args: &Args) -> Result<String, String> {
+    let pages = args
+        .required("snapshot")?
+        .split(',')
+        .map(read_json::<StateSnapshot>)
+        .collect::<Result<Vec<_>, _>>()?;
+    let (header, accounts) = snapshot::verify(pages)?;
+    let stats = snapshot::stats_at(&header, &accounts, args.value("at")?)?;
+    serde_json::to_string_pretty(&stats).map_err(|err| err.to_string())
+}
+
+fn read_code(args: &Args) -> Result<Vec<u8>, String> {
+    let path = args.required("wasm")?;
+    fs::read(path).map_err(|err| format!("{}: {}", path, err))
+}
+
+/// Hash of the code as `stage_code` logs it.
+pub fn code_hash(code: &[u8]) -> Base58CryptoHash {
+    let hash: CryptoHash = Sha256::digest(code).into();
+    hash.into()
+}
+
+fn read(path: &str) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))
+}
+
+fn read_json<T: DeserializeOwned>(path: &str) -> Result<T, String> {
+    serde_json::from_str(&read(path)?).map_err(|err| format!("{}: {}", path, err))
+}
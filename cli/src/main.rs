@@ -0,0 +1,14 @@
+use std::process;
+
+use vault_cli::args::Args;
+
+fn main() {
+    let result = Args::parse(std::env::args().skip(1)).and_then(|args| vault_cli::run(&args));
+    match result {
+        Ok(output) => println!("{}", output),
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! Calls rendered as plain JSON or as a multisig `add_request` payload.
+use near_sdk::base64;
+use near_sdk::serde_json::{json, Value};
+use near_sdk::{AccountId, Balance, Gas};
+use vault_client::FunctionCall;
+
+/// Gas for `stage_code`, mostly for writing the code to storage.
+pub const STAGE_GAS: Gas = 100_000_000_000_000;
+
+/// A call to `receiver_id`, args are raw bytes so that `stage_code` fits too.
+pub struct Action {
+    pub method_name: String,
+    pub args: Vec<u8>,
+    pub gas: Gas,
+    pub deposit: Balance,
+}
+
+impl From<FunctionCall> for Action {
+    fn from(call: FunctionCall) -> Self {
+        Action {
+            args: call.args_bytes(),
+            method_name: call.method_name,
+            gas: call.gas,
+            deposit: call.deposit,
+        }
+    }
+}
+
+/// `stage_code` takes the new code as its raw input.
+pub fn stage_code(code: Vec<u8>) -> Action {
+    Action {
+        method_name: "stage_code".to_string(),
+        args: code,
+        gas: STAGE_GAS,
+        deposit: 0,
+    }
+}
+
+/// Calls for `near call`, args as JSON.
+pub fn to_calls(receiver_id: &str, actions: &[Action]) -> Value {
+    Value::Array(
+        actions
+            .iter()
+            .map(|action| {
+                let args = near_sdk::serde_json::from_slice::<Value>(&action.args)
+                    .unwrap_or_else(|_| json!({ "base64": base64::encode(&action.args) }));
+                json!({
+                    "receiver_id": receiver_id,
+                    "method_name": action.method_name,
+                    "args": args,
+                    "gas": action.gas.to_string(),
+                    "deposit": action.deposit.to_string(),
+                })
+            })
+            .collect(),
+    )
+}
+
+/// Args of `add_request` on a multisig contract, all actions in one request.
+pub fn to_multisig_request(receiver_id: &AccountId, actions: &[Action]) -> Value {
+    json!({
+        "request": {
+            "receiver_id": receiver_id,
+            "actions": actions
+                .iter()
+                .map(|action| json!({
+                    "type": "FunctionCall",
+                    "method_name": action.method_name,
+                    "args": base64::encode(&action.args),
+                    "deposit": action.deposit.to_string(),
+                    "gas": action.gas.to_string(),
+                }))
+                .collect::<Vec<_>>(),
+        }
+    })
+}
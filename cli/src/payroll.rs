@@ -0,0 +1,67 @@
+//! Payroll CSV with a header line and columns
+//! `account_id,start_timestamp,release_interval,release_rounds,release_per_round`,
+//! optionally followed by `label,cost_center,role` of the metadata, empty for none.
+use near_sdk::Balance;
+use vault_client::{AccountMetadata, AddAccountRequest, FunctionCall, TimestampSec};
+
+use crate::args::to_account;
+
+#[derive(Debug, PartialEq)]
+pub struct PayrollEntry {
+    pub account_id: String,
+    pub start_timestamp: TimestampSec,
+    pub release_interval: TimestampSec,
+    pub release_rounds: u32,
+    pub release_per_round: Balance,
+    pub metadata: Option<AccountMetadata>,
+}
+
+impl PayrollEntry {
+    pub fn to_call(&self) -> Result<FunctionCall, String> {
+        let request = AddAccountRequest::new(to_account(&self.account_id)?, self.release_per_round)
+            .start_timestamp(self.start_timestamp)
+            .release_interval(self.release_interval)
+            .release_rounds(self.release_rounds);
+        Ok(match &self.metadata {
+            Some(metadata) => request.metadata(metadata.clone()),
+            None => request,
+        }
+        .build())
+    }
+}
+
+pub fn parse_payroll(content: &str) -> Result<Vec<PayrollEntry>, String> {
+    content
+        .lines()
+        .enumerate()
+        .skip(1)
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            let line_no = index + 1;
+            let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+            if fields.len() != 5 && fields.len() != 8 {
+                return Err(format!("line {}: expect 5 or 8 columns", line_no));
+            }
+            let text = |index: usize| Some(fields[index].to_string()).filter(|field| !field.is_empty());
+            let invalid = |name: &str| format!("line {}: invalid {}", line_no, name);
+            to_account(fields[0]).map_err(|err| format!("line {}: {}", line_no, err))?;
+            Ok(PayrollEntry {
+                account_id: fields[0].to_string(),
+                start_timestamp: fields[1].parse().map_err(|_| invalid("start_timestamp"))?,
+                release_interval: fields[2].parse().map_err(|_| invalid("release_interval"))?,
+                release_rounds: fields[3].parse().map_err(|_| invalid("release_rounds"))?,
+                release_per_round: fields[4].parse().map_err(|_| invalid("release_per_round"))?,
+                metadata: if fields.len() == 8 {
+                    Some(AccountMetadata {
+                        label: text(5),
+                        cost_center: text(6),
+                        role: text(7),
+                        external_ref: None,
+                    })
+                } else {
+                    None
+                },
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,171 @@
+//! Reports computed offline from saved `get_stats` and `list_accounts` output.
+use near_sdk::Balance;
+use vault_client::{AccountOutput, ProjectionPoint, Stats, TimestampSec, MAX_PROJECTION_POINTS};
+use vault_schedule::{claimable_at, round_at, to_nano, unlocked_at, Interval};
+
+/// Block timestamp of `timestamp` in sec, an error if beyond what it can hold.
+pub(crate) fn block_timestamp_of(timestamp: TimestampSec) -> Result<u64, String> {
+    to_nano(timestamp).ok_or_else(|| format!("timestamp {} out of range", timestamp))
+}
+
+/// Sum of balances from saved output, an error naming `what` on overflow.
+pub(crate) fn checked_sum(balances: impl IntoIterator<Item = Balance>, what: &str) -> Result<Balance, String> {
+    balances
+        .into_iter()
+        .try_fold(0, |total: Balance, balance| total.checked_add(balance))
+        .ok_or_else(|| format!("{} overflows", what))
+}
+
+/// Unclaimed amount of the account at `cur_ts`, including refunded balance.
+fn claimable_of(account: &AccountOutput, cur_ts: u64) -> Result<Balance, String> {
+    claimable_at(
+        account.release_per_round.0,
+        account.start_timestamp,
+        Interval::from_parts(account.release_interval, account.release_day),
+        account.release_rounds,
+        account.last_claim_round,
+        cur_ts,
+    )
+    .checked_add(account.refunded_balance.0)
+    .ok_or_else(|| format!("claimable balance of {} overflows", account.account_id))
+}
+
+#[derive(Debug, PartialEq)]
+pub struct VestingRow {
+    pub account_id: String,
+    pub total: Balance,
+    pub vested: Balance,
+    pub claimed: Balance,
+    // vested but not claimed, including refunded balance
+    pub claimable: Balance,
+    pub locked: Balance,
+}
+
+/// Vesting of each beneficiary at `timestamp`, as if nothing is claimed after the dump.
+pub fn vesting_table(accounts: &[AccountOutput], timestamp: TimestampSec) -> Result<Vec<VestingRow>, String> {
+    let cur_ts = block_timestamp_of(timestamp)?;
+    let mut rows: Vec<VestingRow> = accounts
+        .iter()
+        .map(|account| {
+            let per_round = account.release_per_round.0;
+            let interval = Interval::from_parts(account.release_interval, account.release_day);
+            let overflow = || format!("balance of {} overflows", account.account_id);
+            let total = per_round
+                .checked_mul(u128::from(account.release_rounds))
+                .ok_or_else(overflow)?;
+            let round = std::cmp::min(
+                round_at(account.start_timestamp, interval, cur_ts),
+                account.release_rounds,
+            );
+            // no more than total, as round is capped by release_rounds
+            let vested = per_round * u128::from(round);
+            Ok(VestingRow {
+                account_id: account.account_id.clone(),
+                total,
+                vested,
+                claimed: per_round
+                    .checked_mul(u128::from(account.last_claim_round))
+                    .ok_or_else(overflow)?
+                    .saturating_sub(account.refunded_balance.0),
+                claimable: claimable_of(account, cur_ts)?,
+                locked: total - vested,
+            })
+        })
+        .collect::<Result<_, String>>()?;
+    rows.sort_by(|a, b| a.account_id.cmp(&b.account_id));
+    Ok(rows)
+}
+
+/// Same as `get_projection` of the contract, from the dumps,
+/// bounded by the same `MAX_PROJECTION_POINTS`.
+pub fn solvency(
+    stats: &Stats,
+    accounts: &[AccountOutput],
+    from_ts: TimestampSec,
+    to_ts: TimestampSec,
+    step: TimestampSec,
+) -> Result<Vec<ProjectionPoint>, String> {
+    if step == 0 || from_ts > to_ts {
+        return Err("invalid range".to_string());
+    }
+    if (to_ts - from_ts) / step >= u64::from(MAX_PROJECTION_POINTS) {
+        return Err(format!("more than {} points, use a larger step", MAX_PROJECTION_POINTS));
+    }
+    let mut points = vec![];
+    let mut timestamp = from_ts;
+    loop {
+        let cur_ts = block_timestamp_of(timestamp)?;
+        let unlocked = checked_sum(
+            stats.tranches.iter().map(|tranche| {
+                unlocked_at(
+                    tranche.total_balance.0,
+                    tranche.start_timestamp,
+                    Interval::from_parts(tranche.release_interval, tranche.release_day),
+                    tranche.release_rounds,
+                    cur_ts,
+                )
+            }),
+            "unlocked balance",
+        )?;
+        let locked = stats
+            .total_balance
+            .0
+            .checked_sub(unlocked)
+            .ok_or("unlocked balance over total balance")?;
+        let over_delegated = stats.delegated_balance.0.saturating_sub(locked);
+        let liquid = unlocked
+            .saturating_sub(stats.claimed_balance.0)
+            .saturating_sub(over_delegated);
+        let committed = checked_sum(
+            accounts
+                .iter()
+                .map(|account| claimable_of(account, cur_ts))
+                .collect::<Result<Vec<_>, _>>()?,
+            "committed balance",
+        )?;
+        points.push(ProjectionPoint {
+            timestamp,
+            locked_balance: locked.into(),
+            liquid_balance: liquid.into(),
+            committed_balance: committed.into(),
+            shortfall: liquid < committed,
+        });
+        match timestamp.checked_add(step) {
+            Some(next) if next <= to_ts => timestamp = next,
+            _ => break,
+        }
+    }
+    Ok(points)
+}
+
+pub fn render_vesting(rows: &[VestingRow]) -> String {
+    let mut out = format!(
+        "{:<32} {:>24} {:>24} {:>24} {:>24} {:>24}\n",
+        "account_id", "total", "vested", "claimed", "claimable", "locked"
+    );
+    for row in rows {
+        out += &format!(
+            "{:<32} {:>24} {:>24} {:>24} {:>24} {:>24}\n",
+            row.account_id, row.total, row.vested, row.claimed, row.claimable, row.locked
+        );
+    }
+    out
+}
+
+pub fn render_solvency(points: &[ProjectionPoint]) -> String {
+    let mut out = format!(
+        "{:>12} {:>24} {:>24} {:>24} {}\n",
+        "timestamp", "locked", "liquid", "committed", "shortfall"
+    );
+    for point in points {
+        out += &format!(
+            "{:>12} {:>24} {:>24} {:>24} {}\n",
+            point.timestamp,
+            point.locked_balance.0,
+            point.liquid_balance.0,
+            point.committed_balance.0,
+            if point.shortfall { "YES" } else { "" }
+        );
+    }
+    out
+}
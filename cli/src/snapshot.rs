@@ -0,0 +1,151 @@
+//! Offline check of `export_state` pages, and `Stats` recomputed from them.
+use std::cmp::min;
+
+use near_sdk::{Balance, CryptoHash};
+use sha2::{Digest, Sha256};
+use vault_client::{
+    state_bytes, AccountRecord, StateHeader, StateSnapshot, Stats, TimestampSec, TrancheOutput,
+    VaultError, SNAPSHOT_FORMAT,
+};
+use vault_schedule::{claimable_at, round_at, unlocked_at, Interval};
+
+use crate::report::{block_timestamp_of, checked_sum};
+
+/// Joins pages of one snapshot and checks they cover all accounts of the state hashed.
+pub fn verify(pages: Vec<StateSnapshot>) -> Result<(StateHeader, Vec<AccountRecord>), String> {
+    let first = pages.first().ok_or("no snapshot page")?;
+    if first.header.format != SNAPSHOT_FORMAT {
+        return Err(format!("unsupported snapshot format {}", first.header.format));
+    }
+    let (header, state_hash) = (first.header.clone(), first.state_hash);
+    let mut accounts: Vec<AccountRecord> = vec![];
+    for page in pages {
+        if page.header != header || page.state_hash != state_hash {
+            return Err("pages are from different states".to_string());
+        }
+        if page.from_index as usize != accounts.len() {
+            return Err(format!(
+                "expect page from index {}, got {}",
+                accounts.len(),
+                page.from_index
+            ));
+        }
+        accounts.extend(page.accounts);
+    }
+    if accounts.len() != header.total_accounts as usize {
+        return Err(format!(
+            "expect {} accounts, got {}",
+            header.total_accounts,
+            accounts.len()
+        ));
+    }
+    if accounts.windows(2).any(|pair| pair[0].account_id >= pair[1].account_id) {
+        return Err("accounts not sorted by id".to_string());
+    }
+    let hash: CryptoHash = Sha256::digest(&state_bytes(&header, &accounts)).into();
+    if CryptoHash::from(state_hash) != hash {
+        return Err("state hash mismatch".to_string());
+    }
+    Ok((header, accounts))
+}
+
+/// Same as `get_stats` of the contract at `timestamp`, if nothing happened after the export.
+/// Fails where the contract would, e.g. at a time before the claimed balance was released.
+pub fn stats_at(header: &StateHeader, accounts: &[AccountRecord], timestamp: TimestampSec) -> Result<Stats, String> {
+    let cur_ts = block_timestamp_of(timestamp)?;
+    let last = header.tranches.len().checked_sub(1).ok_or("snapshot has no tranche")?;
+    let mut current_round: u32 = 0;
+    let mut unlocked: Balance = 0;
+    let mut tranches = vec![];
+    for (index, tranche) in header.tranches.iter().enumerate() {
+        let round = round_at(tranche.start_timestamp, tranche.interval(), cur_ts);
+        // saturates like the contract does
+        current_round = current_round.saturating_add(if index == last {
+            round
+        } else {
+            min(round, tranche.release_rounds)
+        });
+        let tranche_unlocked = unlocked_at(
+            tranche.total_balance.0,
+            tranche.start_timestamp,
+            tranche.interval(),
+            tranche.release_rounds,
+            cur_ts,
+        );
+        unlocked = unlocked
+            .checked_add(tranche_unlocked)
+            .ok_or("unlocked balance overflows")?;
+        tranches.push(TrancheOutput {
+            start_timestamp: tranche.start_timestamp,
+            release_interval: tranche.release_interval,
+            release_rounds: tranche.release_rounds,
+            total_balance: tranche.total_balance,
+            release_day: tranche.release_day,
+            current_round: min(round, tranche.release_rounds),
+            unlocked_balance: tranche_unlocked.into(),
+            // no more than total by unlocked_at
+            locked_balance: (tranche.total_balance.0 - tranche_unlocked).into(),
+        });
+    }
+    let locked = header
+        .total_balance
+        .0
+        .checked_sub(unlocked)
+        .ok_or("unlocked balance over total balance")?;
+    let over_delegated = header.delegated_balance.0.saturating_sub(locked);
+    let liquid = unlocked
+        .checked_sub(header.claimed_balance.0)
+        .ok_or_else(|| {
+            format!(
+                "{}: claimed balance {} over unlocked {} at {}",
+                VaultError::ClaimedOverUnlocked,
+                header.claimed_balance.0,
+                unlocked,
+                timestamp
+            )
+        })?
+        .saturating_sub(over_delegated);
+    let unclaimed = checked_sum(
+        accounts
+            .iter()
+            .map(|account| {
+                claimable_at(
+                    account.release_per_round.0,
+                    account.start_timestamp,
+                    Interval::from_parts(account.release_interval, account.release_day),
+                    account.release_rounds,
+                    account.last_claim_round,
+                    cur_ts,
+                )
+                .checked_add(account.refunded_balance.0)
+                .ok_or_else(|| format!("claimable balance of {} overflows", account.account_id))
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        "unclaimed balance",
+    )?;
+    Ok(Stats {
+        version: header.version.clone(),
+        owner_id: header.owner_id.clone(),
+        token_account_id: header.token_account_id.clone(),
+        total_balance: header.total_balance,
+        start_timestamp: header.tranches[0].start_timestamp,
+        release_interval: header.tranches[0].release_interval,
+        release_rounds: header
+            .tranches
+            .iter()
+            .fold(0, |rounds: u32, tranche| rounds.saturating_add(tranche.release_rounds)),
+        claimed_balance: header.claimed_balance,
+        locked_balance: locked.into(),
+        liquid_balance: liquid.into(),
+        unclaimed_balance: unclaimed.into(),
+        current_round,
+        storage_pool_balance: header.storage_pool,
+        registration_reserve: header.registration_reserve,
+        keeper_reward: header.keeper_reward,
+        delegated_balance: header.delegated_balance,
+        delegation_limit_bps: header.delegation_limit_bps,
+        delegation_yield: header.delegation_yield,
+        in_flight_balance: header.in_flight_balance,
+        tranches,
+    })
+}
@@ -0,0 +1,195 @@
+use near_sdk::base64;
+use near_sdk::serde_json::{self, Value};
+use vault_cli::args::Args;
+use vault_cli::payroll::parse_payroll;
+use vault_cli::report::{solvency, vesting_table, VestingRow};
+use vault_cli::{code_hash, run};
+use vault_client::{AccountOutput, Stats};
+
+fn data(name: &str) -> String {
+    format!("{}/tests/data/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+fn parse(args: &str) -> Args {
+    Args::parse(args.split_whitespace().map(|arg| arg.to_string())).unwrap()
+}
+
+fn read_json<T: near_sdk::serde::de::DeserializeOwned>(name: &str) -> T {
+    serde_json::from_str(&std::fs::read_to_string(data(name)).unwrap()).unwrap()
+}
+
+#[test]
+fn payment_call() {
+    let output = run(&parse("payment --vault vault --receiver alice --amount 100")).unwrap();
+    let calls: Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(calls[0]["receiver_id"], "vault");
+    assert_eq!(calls[0]["method_name"], "payment");
+    assert_eq!(calls[0]["args"]["receiver_id"], "alice");
+    assert_eq!(calls[0]["args"]["amount"], "100");
+    assert_eq!(calls[0]["deposit"], "0");
+}
+
+#[test]
+fn monthly_account_call() {
+    let output = run(&parse(
+        "add-account --vault vault --account alice --start 1600000000 --day 31 --rounds 12 --per-round 100",
+    ))
+    .unwrap();
+    let calls: Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(calls[0]["method_name"], "add_monthly_account");
+    assert_eq!(calls[0]["args"]["release_day"], 31);
+    assert!(calls[0]["args"].get("release_interval").is_none());
+
+    let output = run(&parse(
+        "add-account --vault vault --account alice --start 1600000000 --interval 60 --rounds 12 --per-round 100",
+    ))
+    .unwrap();
+    let calls: Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(calls[0]["method_name"], "add_account");
+    assert_eq!(calls[0]["args"]["release_interval"], 60);
+}
+
+#[test]
+fn multisig_request() {
+    let output = run(&parse("set-owner --vault vault --owner bob --multisig dao")).unwrap();
+    let request: Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(request["request"]["receiver_id"], "vault");
+    let action = &request["request"]["actions"][0];
+    assert_eq!(action["type"], "FunctionCall");
+    assert_eq!(action["method_name"], "set_owner");
+    let args: Value =
+        serde_json::from_slice(&base64::decode(action["args"].as_str().unwrap()).unwrap()).unwrap();
+    assert_eq!(args["owner_id"], "bob");
+}
+
+#[test]
+fn invalid_args() {
+    assert_eq!(
+        run(&parse("payment --vault vault --receiver alice")).unwrap_err(),
+        "missing --amount"
+    );
+    assert_eq!(
+        run(&parse("payment --vault vault --receiver Alice --amount 1")).unwrap_err(),
+        "invalid account id Alice"
+    );
+    assert!(run(&parse("unknown --vault vault")).is_err());
+    assert!(Args::parse(vec!["payment".to_string(), "--vault".to_string()]).is_err());
+}
+
+#[test]
+fn payroll_batch() {
+    let output = run(&parse(&format!("batch --vault vault --csv {}", data("payroll.csv")))).unwrap();
+    let calls: Value = serde_json::from_str(&output).unwrap();
+    let calls = calls.as_array().unwrap();
+    assert_eq!(calls.len(), 2);
+    assert_eq!(calls[1]["method_name"], "add_account");
+    assert_eq!(calls[1]["args"]["account_id"], "bob");
+    assert_eq!(calls[1]["args"]["release_rounds"], 24);
+    assert_eq!(calls[1]["args"]["release_per_round"], "500000000000000000000");
+    assert_eq!(calls[1]["deposit"], "10000000000000000000000");
+    assert!(calls[1]["args"].get("metadata").is_none());
+    assert_eq!(calls[0]["args"]["metadata"]["label"], "Alice");
+    assert_eq!(calls[0]["args"]["metadata"]["cost_center"], "engineering");
+    assert!(calls[0]["args"]["metadata"]["role"].is_null());
+
+    assert_eq!(
+        parse_payroll("header\nalice,1,2,3\n").unwrap_err(),
+        "line 2: expect 5 or 8 columns"
+    );
+    assert_eq!(
+        parse_payroll("header\n\nalice,1,2,x,4\n").unwrap_err(),
+        "line 3: invalid release_rounds"
+    );
+}
+
+#[test]
+fn vesting_report() {
+    let accounts: Vec<AccountOutput> = read_json("accounts.json");
+    let rows = vesting_table(&accounts, 500).unwrap();
+    assert_eq!(
+        rows,
+        vec![
+            VestingRow {
+                account_id: "alice".to_string(),
+                total: 500,
+                vested: 250,
+                claimed: 100,
+                claimable: 150,
+                locked: 250,
+            },
+            VestingRow {
+                account_id: "bob".to_string(),
+                total: 600,
+                vested: 300,
+                claimed: 0,
+                claimable: 300,
+                locked: 300,
+            },
+        ]
+    );
+
+    let mut accounts = accounts;
+    let bob = accounts.iter().position(|account| account.account_id == "bob").unwrap();
+    accounts[bob].release_per_round.0 = u128::MAX;
+    assert_eq!(vesting_table(&accounts, 500).unwrap_err(), "balance of bob overflows");
+    let stats: Stats = read_json("stats.json");
+    assert_eq!(
+        solvency(&stats, &accounts, 0, 1000, 500).unwrap_err(),
+        "committed balance overflows"
+    );
+}
+
+#[test]
+fn solvency_report() {
+    let stats: Stats = read_json("stats.json");
+    let accounts: Vec<AccountOutput> = read_json("accounts.json");
+    let points = solvency(&stats, &accounts, 0, 1000, 500).unwrap();
+    assert_eq!(points.len(), 3);
+    assert_eq!(points[0].locked_balance.0, 1000);
+    assert!(!points[0].shortfall);
+    assert_eq!(points[1].liquid_balance.0, 400);
+    assert_eq!(points[1].committed_balance.0, 450);
+    assert!(points[1].shortfall);
+    assert_eq!(points[2].liquid_balance.0, 900);
+    assert_eq!(points[2].committed_balance.0, 1000);
+    assert!(solvency(&stats, &accounts, 0, 1000, 0).is_err());
+    assert_eq!(solvency(&stats, &accounts, 0, 499, 1).unwrap().len(), 500);
+    assert_eq!(
+        solvency(&stats, &accounts, 0, 500, 1).unwrap_err(),
+        "more than 500 points, use a larger step"
+    );
+    assert!(solvency(&stats, &accounts, 0, u64::MAX, 1).is_err());
+
+    let output = run(&parse(&format!(
+        "report solvency --stats {} --accounts {} --from 0 --to 1000 --step 500",
+        data("stats.json"),
+        data("accounts.json")
+    )))
+    .unwrap();
+    assert_eq!(output.lines().count(), 4);
+}
+
+#[test]
+fn staged_upgrade_calls() {
+    assert_eq!(
+        String::from(&code_hash(b"")),
+        "GKot5hBsd81kMupNCXHaqbhv3huEbxAFMLnpcX2hniwn"
+    );
+
+    let output = run(&parse(&format!("stage-code --vault vault --wasm {}", data("payroll.csv")))).unwrap();
+    let calls: Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(calls[0]["method_name"], "stage_code");
+    // raw input is not json
+    assert!(calls[0]["args"]["base64"].is_string());
+
+    let code = std::fs::read(data("payroll.csv")).unwrap();
+    let output = run(&parse(&format!("deploy-staged --vault vault --wasm {}", data("payroll.csv")))).unwrap();
+    let calls: Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(calls[0]["method_name"], "deploy_staged_code");
+    assert_eq!(calls[0]["args"]["expected_hash"], String::from(&code_hash(&code)));
+
+    let output = run(&parse("deploy-staged --vault vault --hash GKot5hBsd81kMupNCXHaqbhv3huEbxAFMLnpcX2hniwn")).unwrap();
+    let calls: Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(calls[0]["args"]["expected_hash"], "GKot5hBsd81kMupNCXHaqbhv3huEbxAFMLnpcX2hniwn");
+    assert!(run(&parse("deploy-staged --vault vault --hash 0OIl")).is_err());
+}
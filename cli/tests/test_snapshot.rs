@@ -0,0 +1,153 @@
+use near_sdk::json_types::U128;
+use near_sdk::CryptoHash;
+use sha2::{Digest, Sha256};
+use vault_cli::snapshot::{stats_at, verify};
+use vault_client::{
+    state_bytes, AccountMetadata, AccountRecord, AccountTransfer, StateHeader, StateSnapshot, Tranche,
+    SNAPSHOT_FORMAT,
+};
+
+fn header() -> StateHeader {
+    StateHeader {
+        format: SNAPSHOT_FORMAT,
+        version: "0.3.1".to_string(),
+        owner_id: "owner".to_string(),
+        token_account_id: "token".to_string(),
+        total_balance: U128(1000),
+        tranches: vec![Tranche {
+            start_timestamp: 0,
+            release_interval: 100,
+            release_rounds: 10,
+            total_balance: U128(1000),
+            release_day: None,
+        }],
+        claimed_balance: U128(100),
+        storage_pool: U128(0),
+        registration_reserve: U128(0),
+        keeper_reward: U128(0),
+        delegations: vec![("staking".to_string(), U128(200))],
+        delegation_limit_bps: 5000,
+        delegated_balance: U128(200),
+        delegation_yield: U128(0),
+        recalled: vec![("staking".to_string(), U128(50))],
+        account_transfers: vec![(
+            "alice".to_string(),
+            AccountTransfer { new_account_id: "carol".to_string(), approved: false },
+        )],
+        in_flight_balance: U128(0),
+        claim_receivers: vec!["staking".to_string()],
+        total_accounts: 2,
+    }
+}
+
+fn account(account_id: &str, release_per_round: u128, last_claim_round: u32) -> AccountRecord {
+    AccountRecord {
+        account_id: account_id.to_string(),
+        start_timestamp: 0,
+        release_interval: 100,
+        release_rounds: 10,
+        last_claim_round,
+        release_per_round: U128(release_per_round),
+        release_day: None,
+        storage_balance: U128(0),
+        push_disabled: false,
+        refunded_balance: U128(0),
+        forfeited_balance: U128(0),
+        end_timestamp: None,
+        clawback_reason: None,
+        pending_claims: 0,
+        metadata: AccountMetadata::default(),
+    }
+}
+
+fn pages() -> Vec<StateSnapshot> {
+    let header = header();
+    let accounts = vec![account("alice", 50, 2), account("bob", 60, 0)];
+    let state_hash: CryptoHash = Sha256::digest(&state_bytes(&header, &accounts)).into();
+    accounts
+        .into_iter()
+        .enumerate()
+        .map(|(index, account)| StateSnapshot {
+            header: header.clone(),
+            from_index: index as u32,
+            accounts: vec![account],
+            state_hash: state_hash.into(),
+        })
+        .collect()
+}
+
+#[test]
+fn verify_pages() {
+    let (header, accounts) = verify(pages()).unwrap();
+    assert_eq!(header, self::header());
+    assert_eq!(accounts.len(), 2);
+
+    let mut missing = pages();
+    missing.pop();
+    assert_eq!(verify(missing).unwrap_err(), "expect 2 accounts, got 1");
+
+    let mut reordered = pages();
+    reordered.swap(0, 1);
+    assert!(verify(reordered).is_err());
+
+    let mut tampered = pages();
+    tampered[1].accounts[0].last_claim_round = 5;
+    assert_eq!(verify(tampered).unwrap_err(), "state hash mismatch");
+
+    let mut mixed = pages();
+    mixed[1].header.claimed_balance = U128(0);
+    assert_eq!(verify(mixed).unwrap_err(), "pages are from different states");
+
+    assert!(verify(vec![]).is_err());
+}
+
+#[test]
+fn recompute_stats() {
+    let (header, accounts) = verify(pages()).unwrap();
+
+    let stats = stats_at(&header, &accounts, 500).unwrap();
+    assert_eq!(stats.current_round, 5);
+    assert_eq!(stats.locked_balance.0, 500);
+    assert_eq!(stats.liquid_balance.0, 400);
+    assert_eq!(stats.unclaimed_balance.0, 450);
+    assert_eq!(stats.tranches[0].unlocked_balance.0, 500);
+
+    println!("*** Delegated beyond locked balance isn't liquid");
+    let stats = stats_at(&header, &accounts, 900).unwrap();
+    assert_eq!(stats.locked_balance.0, 100);
+    assert_eq!(stats.liquid_balance.0, 700);
+
+    println!("*** Round counts on after the end");
+    let stats = stats_at(&header, &accounts, 1200).unwrap();
+    assert_eq!(stats.current_round, 12);
+    assert_eq!(stats.tranches[0].current_round, 10);
+    assert_eq!(stats.unclaimed_balance.0, 1000);
+
+    println!("*** Times beyond block timestamps are rejected");
+    assert!(stats_at(&header, &accounts, u64::MAX / 1_000_000_000).is_ok());
+    assert_eq!(
+        stats_at(&header, &accounts, u64::MAX / 1_000_000_000 + 1).unwrap_err(),
+        "timestamp 18446744074 out of range"
+    );
+}
+
+#[test]
+fn stats_of_inconsistent_snapshot() {
+    let (header, accounts) = verify(pages()).unwrap();
+
+    println!("*** Before the claimed balance was released");
+    assert_eq!(
+        stats_at(&header, &accounts, 50).unwrap_err(),
+        "ERR_CLAIMED_OVER_UNLOCKED: claimed balance 100 over unlocked 0 at 50"
+    );
+
+    println!("*** No tranche");
+    let mut empty = header.clone();
+    empty.tranches.clear();
+    assert_eq!(stats_at(&empty, &accounts, 500).unwrap_err(), "snapshot has no tranche");
+
+    println!("*** Balances beyond u128");
+    let mut huge = accounts.clone();
+    huge[1].release_per_round = U128(u128::MAX);
+    assert_eq!(stats_at(&header, &huge, 500).unwrap_err(), "unclaimed balance overflows");
+}
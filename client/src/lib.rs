@@ -0,0 +1,333 @@
+/*!
+* Typed requests and responses of the vault contract.
+*
+* Requests are built into `FunctionCall` or `ViewCall`, which carry the method name
+* and JSON args exactly as the contract expects, e.g. balances as strings.
+* Responses are parsed into the contract's own output types.
+*/
+use near_sdk::json_types::{Base58CryptoHash, ValidAccountId, WrappedBalance};
+use near_sdk::serde::de::DeserializeOwned;
+use near_sdk::serde::Serialize;
+use near_sdk::serde_json::{self, json, Value};
+use near_sdk::{AccountId, Balance, Gas};
+
+pub use vault::{
+    state_bytes, AccountMetadata, AccountOutput, AccountRecord, AccountTransfer, CostCenterOutput,
+    ProjectionPoint, StateHeader, StateSnapshot, Stats, Tranche, TrancheOutput, VaultError,
+    MAX_PROJECTION_POINTS, SNAPSHOT_FORMAT,
+};
+use vault::{GAS_FOR_AFTER_STORAGE_BALANCE_OF, GAS_FOR_STORAGE_VIEW};
+pub use vault_schedule::{Interval, TimestampSec};
+
+pub const DEFAULT_GAS: Gas = 100_000_000_000_000;
+/// Gas for methods that schedule a transfer and its callback.
+pub const TRANSFER_GAS: Gas = 50_000_000_000_000;
+/// Gas for `claim`, covering the registration path of the contract:
+/// two storage views, then registering and transferring in their callback.
+pub const CLAIM_GAS: Gas = 40_000_000_000_000 + 2 * GAS_FOR_STORAGE_VIEW + GAS_FOR_AFTER_STORAGE_BALANCE_OF;
+/// Gas for `deploy_staged_code`, which deploys the code and calls `migrate`.
+pub const DEPLOY_GAS: Gas = 200_000_000_000_000;
+
+/// Storage of an account record is about 0.002 NEAR, the rest stays in storage pool.
+pub const ADD_ACCOUNT_DEPOSIT: Balance = 10_000_000_000_000_000_000_000;
+
+/// A change call to the vault.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionCall {
+    pub method_name: String,
+    pub args: Value,
+    pub gas: Gas,
+    pub deposit: Balance,
+}
+
+impl FunctionCall {
+    fn new<T: Serialize>(method_name: &str, args: T, gas: Gas, deposit: Balance) -> Self {
+        FunctionCall {
+            method_name: method_name.to_string(),
+            args: serde_json::to_value(args).expect("args are always serializable"),
+            gas,
+            deposit,
+        }
+    }
+
+    pub fn with_gas(mut self, gas: Gas) -> Self {
+        self.gas = gas;
+        self
+    }
+
+    pub fn with_deposit(mut self, deposit: Balance) -> Self {
+        self.deposit = deposit;
+        self
+    }
+
+    pub fn args_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(&self.args).unwrap()
+    }
+}
+
+/// A view call to the vault, parse its result with `parse`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewCall {
+    pub method_name: String,
+    pub args: Value,
+}
+
+impl ViewCall {
+    fn new(method_name: &str, args: Value) -> Self {
+        ViewCall {
+            method_name: method_name.to_string(),
+            args,
+        }
+    }
+
+    pub fn args_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(&self.args).unwrap()
+    }
+}
+
+/// Parse raw JSON result of a view call.
+pub fn parse<T: DeserializeOwned>(result: &[u8]) -> Result<T, serde_json::Error> {
+    serde_json::from_slice(result)
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct NewArgs {
+    owner_id: ValidAccountId,
+    token_account_id: ValidAccountId,
+    total_balance: WrappedBalance,
+    start_timestamp: TimestampSec,
+    release_interval: TimestampSec,
+    release_rounds: u32,
+}
+
+/// Builder of `new`, release defaults to a single round starting at 0.
+pub struct NewRequest {
+    args: NewArgs,
+}
+
+impl NewRequest {
+    pub fn new(owner_id: ValidAccountId, token_account_id: ValidAccountId, total_balance: Balance) -> Self {
+        NewRequest {
+            args: NewArgs {
+                owner_id,
+                token_account_id,
+                total_balance: total_balance.into(),
+                start_timestamp: 0,
+                release_interval: 1,
+                release_rounds: 1,
+            },
+        }
+    }
+
+    pub fn start_timestamp(mut self, start_timestamp: TimestampSec) -> Self {
+        self.args.start_timestamp = start_timestamp;
+        self
+    }
+
+    pub fn release_interval(mut self, release_interval: TimestampSec) -> Self {
+        self.args.release_interval = release_interval;
+        self
+    }
+
+    pub fn release_rounds(mut self, release_rounds: u32) -> Self {
+        self.args.release_rounds = release_rounds;
+        self
+    }
+
+    pub fn build(self) -> FunctionCall {
+        FunctionCall::new("new", self.args, DEFAULT_GAS, 0)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct AddAccountArgs {
+    account_id: ValidAccountId,
+    start_timestamp: TimestampSec,
+    release_interval: TimestampSec,
+    release_rounds: u32,
+    release_per_round: WrappedBalance,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<AccountMetadata>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct AddMonthlyAccountArgs {
+    account_id: ValidAccountId,
+    start_timestamp: TimestampSec,
+    release_day: u8,
+    release_rounds: u32,
+    release_per_round: WrappedBalance,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<AccountMetadata>,
+}
+
+/// Builder of `add_account`, release defaults to a single round starting at 0,
+/// attaching `ADD_ACCOUNT_DEPOSIT` for storage.
+/// With `release_day` it builds `add_monthly_account` instead.
+pub struct AddAccountRequest {
+    args: AddAccountArgs,
+    release_day: Option<u8>,
+    deposit: Balance,
+}
+
+impl AddAccountRequest {
+    pub fn new(account_id: ValidAccountId, release_per_round: Balance) -> Self {
+        AddAccountRequest {
+            args: AddAccountArgs {
+                account_id,
+                start_timestamp: 0,
+                release_interval: 1,
+                release_rounds: 1,
+                release_per_round: release_per_round.into(),
+                metadata: None,
+            },
+            release_day: None,
+            deposit: ADD_ACCOUNT_DEPOSIT,
+        }
+    }
+
+    pub fn start_timestamp(mut self, start_timestamp: TimestampSec) -> Self {
+        self.args.start_timestamp = start_timestamp;
+        self
+    }
+
+    pub fn release_interval(mut self, release_interval: TimestampSec) -> Self {
+        self.args.release_interval = release_interval;
+        self
+    }
+
+    pub fn release_rounds(mut self, release_rounds: u32) -> Self {
+        self.args.release_rounds = release_rounds;
+        self
+    }
+
+    /// Rounds end on this day of each month, `release_interval` is ignored.
+    pub fn release_day(mut self, release_day: u8) -> Self {
+        self.release_day = Some(release_day);
+        self
+    }
+
+    pub fn metadata(mut self, metadata: AccountMetadata) -> Self {
+        self.args.metadata = Some(metadata);
+        self
+    }
+
+    pub fn deposit(mut self, deposit: Balance) -> Self {
+        self.deposit = deposit;
+        self
+    }
+
+    pub fn build(self) -> FunctionCall {
+        match self.release_day {
+            Some(release_day) => {
+                let args = AddMonthlyAccountArgs {
+                    account_id: self.args.account_id,
+                    start_timestamp: self.args.start_timestamp,
+                    release_day,
+                    release_rounds: self.args.release_rounds,
+                    release_per_round: self.args.release_per_round,
+                    metadata: self.args.metadata,
+                };
+                FunctionCall::new("add_monthly_account", args, DEFAULT_GAS, self.deposit)
+            }
+            None => FunctionCall::new("add_account", self.args, DEFAULT_GAS, self.deposit),
+        }
+    }
+}
+
+/// Calls and views of a deployed vault.
+pub struct VaultClient {
+    pub contract_id: AccountId,
+}
+
+impl VaultClient {
+    pub fn new(contract_id: AccountId) -> Self {
+        VaultClient { contract_id }
+    }
+
+    pub fn payment(&self, receiver_id: ValidAccountId, amount: Balance) -> FunctionCall {
+        FunctionCall::new(
+            "payment",
+            json!({ "receiver_id": receiver_id, "amount": WrappedBalance::from(amount) }),
+            TRANSFER_GAS,
+            0,
+        )
+    }
+
+    pub fn set_owner(&self, owner_id: ValidAccountId) -> FunctionCall {
+        FunctionCall::new("set_owner", json!({ "owner_id": owner_id }), DEFAULT_GAS, 0)
+    }
+
+    pub fn remove_account(&self, account_id: ValidAccountId) -> FunctionCall {
+        FunctionCall::new("remove_account", json!({ "account_id": account_id }), DEFAULT_GAS, 0)
+    }
+
+    /// Attaches `ADD_ACCOUNT_DEPOSIT` in case the record grows, the rest stays in storage pool.
+    pub fn update_account_metadata(&self, account_id: ValidAccountId, metadata: AccountMetadata) -> FunctionCall {
+        FunctionCall::new(
+            "update_account_metadata",
+            json!({ "account_id": account_id, "metadata": metadata }),
+            DEFAULT_GAS,
+            ADD_ACCOUNT_DEPOSIT,
+        )
+    }
+
+    pub fn claim(&self) -> FunctionCall {
+        FunctionCall::new("claim", json!({}), CLAIM_GAS, 0)
+    }
+
+    pub fn get_stats(&self) -> ViewCall {
+        ViewCall::new("get_stats", json!({}))
+    }
+
+    pub fn get_account(&self, account_id: ValidAccountId) -> ViewCall {
+        ViewCall::new("get_account", json!({ "account_id": account_id }))
+    }
+
+    pub fn list_accounts(&self) -> ViewCall {
+        ViewCall::new("list_accounts", json!({}))
+    }
+
+    pub fn get_cost_center_totals(&self) -> ViewCall {
+        ViewCall::new("get_cost_center_totals", json!({}))
+    }
+
+    pub fn get_projection(&self, from_ts: TimestampSec, to_ts: TimestampSec, step: TimestampSec) -> ViewCall {
+        ViewCall::new(
+            "get_projection",
+            json!({ "from_ts": from_ts, "to_ts": to_ts, "step": step }),
+        )
+    }
+
+    pub fn export_state(&self, from_index: u32, limit: u32) -> ViewCall {
+        ViewCall::new("export_state", json!({ "from_index": from_index, "limit": limit }))
+    }
+
+    pub fn deploy_staged_code(&self, expected_hash: Base58CryptoHash) -> FunctionCall {
+        FunctionCall::new(
+            "deploy_staged_code",
+            json!({ "expected_hash": expected_hash }),
+            DEPLOY_GAS,
+            0,
+        )
+    }
+
+    pub fn cancel_staged_code(&self) -> FunctionCall {
+        FunctionCall::new("cancel_staged_code", json!({}), DEFAULT_GAS, 0)
+    }
+
+    pub fn get_staged_code_hash(&self) -> ViewCall {
+        ViewCall::new("get_staged_code_hash", json!({}))
+    }
+
+    pub fn set_deployed_code_hash(&self, code_hash: Base58CryptoHash) -> FunctionCall {
+        FunctionCall::new("set_deployed_code_hash", json!({ "code_hash": code_hash }), DEFAULT_GAS, 0)
+    }
+
+    pub fn get_deployed_code_hash(&self) -> ViewCall {
+        ViewCall::new("get_deployed_code_hash", json!({}))
+    }
+}
@@ -0,0 +1,122 @@
+use std::convert::TryFrom;
+use near_sdk::json_types::{ValidAccountId, U128};
+use near_sdk_sim::{init_simulator, to_yocto, UserAccount};
+use vault_client::*;
+
+near_sdk_sim::lazy_static_include::lazy_static_include_bytes! {
+    TEST_TOKEN_WASM_BYTES => "../res/test_token.wasm",
+    VAULT_WASM_BYTES => "../res/vault_release.wasm",
+}
+
+fn to_va(a: &str) -> ValidAccountId {
+    ValidAccountId::try_from(a).unwrap()
+}
+
+fn call(signer: &UserAccount, contract_id: &str, call: &FunctionCall) {
+    signer
+        .call(contract_id.to_string(), &call.method_name, &call.args_bytes(), call.gas, call.deposit)
+        .assert_success();
+}
+
+fn view<T: near_sdk::serde::de::DeserializeOwned>(signer: &UserAccount, contract_id: &str, call: &ViewCall) -> T {
+    let result = signer.view(contract_id.to_string(), &call.method_name, &call.args_bytes());
+    parse(&result.unwrap()).unwrap()
+}
+
+#[test]
+fn roundtrip_against_contract() {
+    let root = init_simulator(None);
+    let owner = root.create_user("owner".to_string(), to_yocto("100"));
+    let user1 = root.create_user("user1".to_string(), to_yocto("10"));
+    let token = root.deploy(&TEST_TOKEN_WASM_BYTES, "test_token".to_string(), to_yocto("10"));
+    token.call("test_token".to_string(), "new", b"{}", DEFAULT_GAS, 0).assert_success();
+    for account_id in &["vault", "owner"] {
+        let args = format!("{{\"account_id\": \"{}\"}}", account_id);
+        root.call("test_token".to_string(), "storage_deposit", args.as_bytes(), DEFAULT_GAS, to_yocto("1"))
+            .assert_success();
+    }
+
+    println!("*** Init through client");
+    let vault = root.deploy(&VAULT_WASM_BYTES, "vault".to_string(), to_yocto("10"));
+    let new = NewRequest::new(to_va("owner"), to_va("test_token"), 10000)
+        .start_timestamp(50)
+        .release_interval(10)
+        .release_rounds(10)
+        .build();
+    call(&vault, "vault", &new);
+
+    let client = VaultClient::new("vault".to_string());
+    let stats: Stats = view(&root, "vault", &client.get_stats());
+    assert_eq!(stats.owner_id, "owner".to_string());
+    assert_eq!(stats.total_balance, U128(10000));
+    assert_eq!(stats.start_timestamp, 50);
+    assert_eq!(stats.release_interval, 10);
+    assert_eq!(stats.release_rounds, 10);
+
+    println!("*** Add account through client");
+    let add_account = AddAccountRequest::new(user1.valid_account_id(), 20)
+        .start_timestamp(50)
+        .release_interval(10)
+        .release_rounds(10)
+        .build();
+    assert_eq!(add_account.args["release_per_round"], "20");
+    call(&owner, "vault", &add_account);
+    let account: Option<AccountOutput> = view(&root, "vault", &client.get_account(user1.valid_account_id()));
+    let account = account.unwrap();
+    assert_eq!(account.account_id, user1.account_id());
+    assert_eq!(account.release_per_round, U128(20));
+    assert_eq!(account.release_rounds, 10);
+    let accounts: Vec<AccountOutput> = view(&root, "vault", &client.list_accounts());
+    assert_eq!(accounts, vec![account]);
+
+    println!("*** Metadata through client");
+    let metadata = AccountMetadata {
+        cost_center: Some("engineering".to_string()),
+        ..Default::default()
+    };
+    call(&owner, "vault", &client.update_account_metadata(user1.valid_account_id(), metadata.clone()));
+    let totals: Vec<CostCenterOutput> = view(&root, "vault", &client.get_cost_center_totals());
+    assert_eq!(totals[0].cost_center, metadata.cost_center);
+    assert_eq!(totals[0].total_balance, U128(200));
+
+    println!("*** Payment through client");
+    owner.call("test_token".to_string(), "mint", b"{\"amount\": \"10000\"}", DEFAULT_GAS, 0).assert_success();
+    owner.call(
+        "test_token".to_string(),
+        "ft_transfer",
+        b"{\"receiver_id\": \"vault\", \"amount\": \"10000\"}",
+        DEFAULT_GAS,
+        1,
+    ).assert_success();
+    assert!(root.borrow_runtime_mut().produce_blocks(150).is_ok());
+    call(&owner, "vault", &client.payment(owner.valid_account_id(), 100));
+    let stats: Stats = view(&root, "vault", &client.get_stats());
+    assert_eq!(stats.claimed_balance, U128(100));
+
+    let points: Vec<ProjectionPoint> = view(&root, "vault", &client.get_projection(50, 150, 50));
+    assert_eq!(points.len(), 3);
+
+    println!("*** Claim through client registers the user from reserve");
+    assert!(CLAIM_GAS >= 100_000_000_000_000);
+    owner.call("vault".to_string(), "registration_reserve_deposit", b"{}", DEFAULT_GAS, to_yocto("1"))
+        .assert_success();
+    call(&user1, "vault", &client.claim());
+    let balance: U128 = near_sdk::serde_json::from_slice(
+        &root
+            .view(
+                "test_token".to_string(),
+                "ft_balance_of",
+                format!("{{\"account_id\": \"{}\"}}", user1.account_id()).as_bytes(),
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(balance, U128(200));
+    let stats: Stats = view(&root, "vault", &client.get_stats());
+    assert!(stats.registration_reserve.0 < to_yocto("1"));
+
+    println!("*** Set owner through client");
+    call(&owner, "vault", &client.set_owner(user1.valid_account_id()));
+    let stats: Stats = view(&root, "vault", &client.get_stats());
+    assert_eq!(stats.owner_id, user1.account_id());
+}
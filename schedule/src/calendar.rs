@@ -0,0 +1,92 @@
+//! Civil dates in the proleptic Gregorian calendar, UTC, after
+//! Howard Hinnant's `days_from_civil` and `civil_from_days`.
+use core::convert::TryFrom;
+
+use crate::TimestampSec;
+
+const SECS_PER_DAY: u64 = 24 * 3600;
+
+/// Days since 1970-01-01 of the given date, `month` from 1 to 12.
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    // months counted from March, so the leap day is the last of the year
+    let month_from_march = i64::from((month + 9) % 12);
+    let day_of_year = (153 * month_from_march + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Year, month from 1 to 12 and day of the date `days` since 1970-01-01.
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = if days >= 0 { days } else { days - 146_096 } / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    } as u32;
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+pub fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        4 | 6 | 9 | 11 => 30,
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        _ => 31,
+    }
+}
+
+/// Months since year 0 of the month `timestamp` (in sec) falls in.
+fn month_index(timestamp: TimestampSec) -> i64 {
+    // u64::MAX / SECS_PER_DAY always fits
+    let (year, month, _) = civil_from_days((timestamp / SECS_PER_DAY) as i64);
+    year * 12 + i64::from(month) - 1
+}
+
+/// Timestamp in sec of `day` of the month at `index`, capped by the month length.
+/// None if beyond `TimestampSec`.
+fn month_day_timestamp(index: i64, day: u8) -> Option<TimestampSec> {
+    let (year, month) = (index.div_euclid(12), index.rem_euclid(12) as u32 + 1);
+    let day = core::cmp::min(core::cmp::max(u32::from(day), 1), days_in_month(year, month));
+    u64::try_from(days_from_civil(year, month, day))
+        .ok()?
+        .checked_mul(SECS_PER_DAY)
+}
+
+/// Index of the first month whose `day` comes after `start`.
+fn first_month_after(start: TimestampSec, day: u8) -> i64 {
+    let index = month_index(start);
+    if !matches!(month_day_timestamp(index, day), Some(timestamp) if timestamp <= start) {
+        index
+    } else {
+        index + 1
+    }
+}
+
+/// Count of month days `day` in `(start, cur]`, both in sec.
+pub(crate) fn months_between(start: TimestampSec, day: u8, cur: TimestampSec) -> u64 {
+    let first = first_month_after(start, day);
+    let index = month_index(cur);
+    let last = if matches!(month_day_timestamp(index, day), Some(timestamp) if timestamp <= cur) {
+        index
+    } else {
+        index - 1
+    };
+    u64::try_from(last - first + 1).unwrap_or(0)
+}
+
+/// Timestamp in sec of the `n`-th month day `day` after `start`, `n` from 1.
+/// None if beyond `TimestampSec`.
+pub(crate) fn nth_month_day_after(start: TimestampSec, day: u8, n: u32) -> Option<TimestampSec> {
+    month_day_timestamp(first_month_after(start, day) + i64::from(n) - 1, day)
+}
@@ -0,0 +1,147 @@
+/*!
+* Release schedule math of the vault, shared by the contract and off-chain tools.
+*
+* A linear release starts at `start_timestamp` (in sec), and releases equally
+* each `release_interval` for `release_rounds` rounds. The interval is either
+* a count of seconds, or a calendar month ending on a given day in UTC.
+* All functions take the current time explicitly in nano seconds,
+* the same unit as block timestamp, and never overflow.
+* Seconds are `u64`, so times beyond what nano seconds can hold, after year 2554,
+* are never reached rather than wrapped around.
+*/
+#![no_std]
+
+use core::convert::TryFrom;
+
+mod calendar;
+
+pub use calendar::{civil_from_days, days_from_civil, days_in_month};
+
+pub type TimestampSec = u64;
+
+const NANO_PER_SEC: u64 = 1_000_000_000;
+
+/// Nano seconds of `timestamp` in sec, None if beyond `u64`.
+pub fn to_nano(timestamp: TimestampSec) -> Option<u64> {
+    timestamp.checked_mul(NANO_PER_SEC)
+}
+
+/// Length of each release round.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interval {
+    /// A fixed count of seconds.
+    Seconds(TimestampSec),
+    /// Rounds end on the given day of each month at 00:00 UTC,
+    /// on the last day of months shorter than that.
+    /// The first round ends on the first such day after start.
+    MonthDay(u8),
+}
+
+impl Interval {
+    /// Monthly on `release_day` if given, otherwise every `release_interval` seconds.
+    pub fn from_parts(release_interval: TimestampSec, release_day: Option<u8>) -> Self {
+        match release_day {
+            Some(day) => Interval::MonthDay(day),
+            None => Interval::Seconds(release_interval),
+        }
+    }
+
+    /// Rounds have some length, a month day is from 1 to 31.
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            Interval::Seconds(seconds) => seconds > 0,
+            Interval::MonthDay(day) => (1..=31).contains(&day),
+        }
+    }
+}
+
+impl From<TimestampSec> for Interval {
+    fn from(seconds: TimestampSec) -> Self {
+        Interval::Seconds(seconds)
+    }
+}
+
+/// Rounds passed since `start_timestamp` at `cur_ts`, not capped by release rounds,
+/// saturated at `u32::MAX`. With zero interval, every round has passed once started.
+pub fn round_at(start_timestamp: TimestampSec, release_interval: impl Into<Interval>, cur_ts: u64) -> u32 {
+    let start = match to_nano(start_timestamp) {
+        Some(start) if cur_ts > start => start,
+        _ => return 0,
+    };
+    let rounds = match release_interval.into() {
+        Interval::Seconds(0) => return u32::MAX,
+        Interval::Seconds(seconds) => match to_nano(seconds) {
+            Some(interval) => (cur_ts - start) / interval,
+            // longer than any span of block timestamps
+            None => 0,
+        },
+        Interval::MonthDay(day) => calendar::months_between(start_timestamp, day, cur_ts / NANO_PER_SEC),
+    };
+    u32::try_from(rounds).unwrap_or(u32::MAX)
+}
+
+/// When `round_at` reaches the given round, i.e. that many intervals after `start_timestamp`,
+/// `start_timestamp` itself for round 0.
+/// None if that's beyond `TimestampSec`.
+pub fn round_timestamp(
+    start_timestamp: TimestampSec,
+    release_interval: impl Into<Interval>,
+    round: u32,
+) -> Option<TimestampSec> {
+    match release_interval.into() {
+        Interval::Seconds(seconds) => seconds
+            .checked_mul(u64::from(round))
+            .and_then(|duration| start_timestamp.checked_add(duration)),
+        Interval::MonthDay(_) if round == 0 => Some(start_timestamp),
+        Interval::MonthDay(day) => calendar::nth_month_day_after(start_timestamp, day, round),
+    }
+}
+
+/// Amount released out of `total_balance` at `cur_ts`, rounded down.
+pub fn unlocked_at(
+    total_balance: u128,
+    start_timestamp: TimestampSec,
+    release_interval: impl Into<Interval>,
+    release_rounds: u32,
+    cur_ts: u64,
+) -> u128 {
+    let round = round_at(start_timestamp, release_interval, cur_ts);
+    if round >= release_rounds {
+        return total_balance;
+    }
+    // floor(total * round / rounds) without the wide multiplication
+    let rounds = u128::from(release_rounds);
+    let round = u128::from(round);
+    total_balance / rounds * round + total_balance % rounds * round / rounds
+}
+
+/// Rounds released but not claimed yet, after `last_claim_round` has been claimed.
+pub fn claimable_rounds_at(
+    start_timestamp: TimestampSec,
+    release_interval: impl Into<Interval>,
+    release_rounds: u32,
+    last_claim_round: u32,
+    cur_ts: u64,
+) -> u32 {
+    let round = round_at(start_timestamp, release_interval, cur_ts);
+    core::cmp::min(round, release_rounds).saturating_sub(last_claim_round)
+}
+
+/// Amount released but not claimed yet, with `release_per_round` each round.
+pub fn claimable_at(
+    release_per_round: u128,
+    start_timestamp: TimestampSec,
+    release_interval: impl Into<Interval>,
+    release_rounds: u32,
+    last_claim_round: u32,
+    cur_ts: u64,
+) -> u128 {
+    let rounds = claimable_rounds_at(
+        start_timestamp,
+        release_interval,
+        release_rounds,
+        last_claim_round,
+        cur_ts,
+    );
+    release_per_round.saturating_mul(u128::from(rounds))
+}
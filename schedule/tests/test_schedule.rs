@@ -0,0 +1,208 @@
+use proptest::prelude::*;
+use vault_schedule::*;
+
+const NANO: u64 = 1_000_000_000;
+
+#[test]
+fn linear_release() {
+    // 1000 over 10 rounds of 10s from 50
+    assert_eq!(round_at(50, 10, 0), 0);
+    assert_eq!(round_at(50, 10, 50 * NANO), 0);
+    assert_eq!(round_at(50, 10, 69 * NANO), 1);
+    assert_eq!(round_at(50, 10, 1000 * NANO), 95);
+    assert_eq!(unlocked_at(1000, 50, 10, 10, 75 * NANO), 200);
+    assert_eq!(unlocked_at(1000, 50, 10, 10, 1000 * NANO), 1000);
+    assert_eq!(unlocked_at(10, 50, 10, 3, 60 * NANO), 3);
+    assert_eq!(claimable_rounds_at(50, 10, 10, 2, 75 * NANO), 0);
+    assert_eq!(claimable_rounds_at(50, 10, 10, 2, 1000 * NANO), 8);
+    assert_eq!(claimable_at(100, 50, 10, 10, 2, 1000 * NANO), 800);
+    assert_eq!(round_at(50, 0, 51 * NANO), u32::MAX);
+}
+
+// 00:00 UTC of some dates
+const JAN_15_2024: TimestampSec = 1_705_276_800;
+const JAN_31_2024: TimestampSec = 1_706_659_200;
+const FEB_01_2024: TimestampSec = 1_706_745_600;
+const FEB_29_2024: TimestampSec = 1_709_164_800;
+const MAR_31_2024: TimestampSec = 1_711_843_200;
+const APR_30_2024: TimestampSec = 1_714_435_200;
+
+#[test]
+fn civil_dates() {
+    assert_eq!(days_from_civil(1970, 1, 1), 0);
+    assert_eq!(days_from_civil(2024, 2, 29), 19_782);
+    assert_eq!(days_from_civil(2000, 2, 29), 11_016);
+    assert_eq!(days_from_civil(2100, 3, 1), 47_541);
+    assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+    assert_eq!(civil_from_days(47_540), (2100, 2, 28));
+    assert_eq!(civil_from_days(47_541), (2100, 3, 1));
+    assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    assert_eq!(days_in_month(2024, 2), 29);
+    assert_eq!(days_in_month(2023, 2), 28);
+    assert_eq!(days_in_month(2000, 2), 29);
+    assert_eq!(days_in_month(2100, 2), 28);
+    assert_eq!(days_in_month(2024, 4), 30);
+    assert_eq!(days_in_month(2024, 12), 31);
+}
+
+#[test]
+fn monthly_release() {
+    let first = Interval::MonthDay(1);
+    // started mid month, the first round ends on the 1st of next month
+    assert_eq!(round_at(JAN_15_2024, first, JAN_31_2024 * NANO), 0);
+    assert_eq!(round_at(JAN_15_2024, first, FEB_01_2024 * NANO - 1), 0);
+    assert_eq!(round_at(JAN_15_2024, first, FEB_01_2024 * NANO), 1);
+    assert_eq!(round_timestamp(JAN_15_2024, first, 1), Some(FEB_01_2024));
+    // started right on the day, that day doesn't count
+    assert_eq!(round_at(FEB_01_2024, first, FEB_01_2024 * NANO), 0);
+    assert_eq!(round_timestamp(FEB_01_2024, first, 0), Some(FEB_01_2024));
+    assert_eq!(round_at(FEB_01_2024, first, 1_000 * 24 * 3600 * NANO + FEB_01_2024 * NANO), 32);
+    assert_eq!(unlocked_at(1200, JAN_15_2024, first, 12, FEB_29_2024 * NANO), 100);
+    assert_eq!(claimable_at(100, JAN_15_2024, first, 12, 0, APR_30_2024 * NANO), 300);
+}
+
+#[test]
+fn monthly_release_at_month_end() {
+    let last = Interval::MonthDay(31);
+    // the 31st falls back to the last day of shorter months, 29th in a leap February
+    assert_eq!(round_timestamp(JAN_15_2024, last, 1), Some(JAN_31_2024));
+    assert_eq!(round_timestamp(JAN_15_2024, last, 2), Some(FEB_29_2024));
+    assert_eq!(round_timestamp(JAN_15_2024, last, 3), Some(MAR_31_2024));
+    assert_eq!(round_timestamp(JAN_15_2024, last, 4), Some(APR_30_2024));
+    assert_eq!(round_at(JAN_15_2024, last, FEB_29_2024 * NANO - 1), 1);
+    assert_eq!(round_at(JAN_15_2024, last, FEB_29_2024 * NANO), 2);
+    // the 30th in February is its last day, not a leap one in 2100
+    assert_eq!(round_timestamp(4_105_036_800, Interval::MonthDay(30), 1), Some(4_107_456_000));
+    // from a leap day to the 29th of each month
+    assert_eq!(round_timestamp(FEB_29_2024, Interval::MonthDay(29), 1), Some(1_711_670_400));
+    // started on the last day of a short month, the 31st of it has passed
+    assert_eq!(round_timestamp(APR_30_2024, last, 1), Some(1_717_113_600));
+    // no month day of the last month fits in u64 seconds
+    assert_eq!(round_timestamp(u64::MAX - 1, last, 1), None);
+}
+
+#[test]
+fn timestamp_boundaries() {
+    // beyond u32 seconds, after 2106-02-07
+    let after_2106 = u64::from(u32::MAX) + 1;
+    assert_eq!(round_at(after_2106, 10, (after_2106 + 25) * NANO), 2);
+    assert_eq!(round_timestamp(after_2106, 10, 2), Some(after_2106 + 20));
+    assert_eq!(round_timestamp(0, Interval::MonthDay(1), 1), Some(31 * 24 * 3600));
+    // nano seconds of u64 end in 2554
+    let last_nano_sec = u64::MAX / NANO;
+    assert_eq!(to_nano(last_nano_sec), Some(last_nano_sec * NANO));
+    assert_eq!(to_nano(last_nano_sec + 1), None);
+    // a start never reached by block timestamps
+    assert_eq!(round_at(last_nano_sec + 1, 10, u64::MAX), 0);
+    assert_eq!(round_at(u64::MAX, 0, u64::MAX), 0);
+    assert_eq!(round_at(u64::MAX, Interval::MonthDay(31), u64::MAX), 0);
+    // an interval longer than any span of block timestamps
+    assert_eq!(round_at(0, last_nano_sec + 1, u64::MAX), 0);
+    assert_eq!(round_at(0, last_nano_sec, u64::MAX), 1);
+    // rounds saturate rather than wrap
+    assert_eq!(round_at(0, 1, u64::MAX), u32::MAX);
+    assert_eq!(round_at(0, 1, u64::from(u32::MAX) * NANO), u32::MAX);
+    // would be 0 if truncated
+    assert_eq!(round_at(0, 1, (u64::from(u32::MAX) + 1) * NANO), u32::MAX);
+    assert_eq!(round_at(0, 0, 1), u32::MAX);
+    assert_eq!(round_timestamp(u64::MAX - 5, 3, 2), None);
+    assert_eq!(round_timestamp(u64::MAX - 6, 3, 2), Some(u64::MAX));
+    assert_eq!(round_timestamp(1, u64::MAX, u32::MAX), None);
+    assert_eq!(unlocked_at(1000, after_2106, 10, 10, u64::MAX), 1000);
+}
+
+proptest! {
+    #[test]
+    fn monthly_rounds_end_at_round_timestamp(
+        start in 0..16_000_000_000u64,
+        day in 1..=31u8,
+        round in 1..600u32,
+    ) {
+        let interval = Interval::MonthDay(day);
+        let end = round_timestamp(start, interval, round).unwrap();
+        let prev = round_timestamp(start, interval, round - 1).unwrap();
+        prop_assert!(end > prev);
+        prop_assert!(end - prev <= 31 * 24 * 3600 + 24 * 3600);
+        prop_assert_eq!(round_at(start, interval, end * NANO), round);
+        prop_assert_eq!(round_at(start, interval, end * NANO - 1), round - 1);
+    }
+
+    #[test]
+    fn monthly_rounds_never_decrease(
+        start in any::<u64>(),
+        day in 1..=31u8,
+        ts in any::<u64>(),
+        delta in any::<u64>(),
+    ) {
+        let interval = Interval::MonthDay(day);
+        prop_assert!(round_at(start, interval, ts) <= round_at(start, interval, ts.saturating_add(delta)));
+    }
+
+    #[test]
+    fn civil_roundtrip(days in -1_000_000i64..1_000_000) {
+        let (year, month, day) = civil_from_days(days);
+        prop_assert!(day >= 1 && day <= days_in_month(year, month));
+        prop_assert_eq!(days_from_civil(year, month, day), days);
+    }
+
+    #[test]
+    fn unlocked_never_exceeds_total(
+        total in any::<u128>(),
+        start in any::<u64>(),
+        interval in any::<u64>(),
+        rounds in 1..=u32::MAX,
+        ts in any::<u64>(),
+    ) {
+        prop_assert!(unlocked_at(total, start, interval, rounds, ts) <= total);
+    }
+
+    #[test]
+    fn unlocked_never_decreases(
+        total in any::<u128>(),
+        start in any::<u64>(),
+        interval in any::<u64>(),
+        rounds in 1..=u32::MAX,
+        ts in any::<u64>(),
+        delta in any::<u64>(),
+    ) {
+        let later = ts.saturating_add(delta);
+        prop_assert!(
+            unlocked_at(total, start, interval, rounds, ts)
+                <= unlocked_at(total, start, interval, rounds, later)
+        );
+    }
+
+    #[test]
+    fn unlocked_is_exact_floor(
+        total in any::<u64>(),
+        start in 0..1_000_000u64,
+        interval in 1..1_000u64,
+        rounds in 1..1_000u32,
+        ts in any::<u64>(),
+    ) {
+        let round = u128::from(core::cmp::min(round_at(start, interval, ts), rounds));
+        let expected = u128::from(total) * round / u128::from(rounds);
+        prop_assert_eq!(unlocked_at(u128::from(total), start, interval, rounds, ts), expected);
+    }
+
+    #[test]
+    fn claimable_never_decreases_and_is_bounded(
+        per_round in any::<u128>(),
+        start in any::<u64>(),
+        interval in any::<u64>(),
+        rounds in any::<u32>(),
+        last_claim in any::<u32>(),
+        ts in any::<u64>(),
+        delta in any::<u64>(),
+    ) {
+        let later = ts.saturating_add(delta);
+        let now_rounds = claimable_rounds_at(start, interval, rounds, last_claim, ts);
+        let later_rounds = claimable_rounds_at(start, interval, rounds, last_claim, later);
+        prop_assert!(now_rounds <= later_rounds);
+        prop_assert!(later_rounds <= rounds.saturating_sub(last_claim));
+        prop_assert!(
+            claimable_at(per_round, start, interval, rounds, last_claim, ts)
+                <= claimable_at(per_round, start, interval, rounds, last_claim, later)
+        );
+    }
+}
@@ -0,0 +1,97 @@
+/*!
+* Mock staking contract accepting NEP-141 token through ft_transfer_call
+*
+*/
+use near_contract_standards::fungible_token::core_impl::ext_fungible_token;
+use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{ValidAccountId, U128};
+use near_sdk::serde_json::{self, Value};
+use near_sdk::{env, near_bindgen, log, AccountId, Balance, Gas, PanicOnDefault, Promise, PromiseOrValue};
+use std::collections::HashMap;
+
+
+near_sdk::setup_alloc!();
+
+const GAS_FOR_FT_TRANSFER_CALL: Gas = 80_000_000_000_000;
+
+#[near_bindgen]
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
+pub struct Contract {
+    pub token_account_id: AccountId,
+    pub staked: HashMap<AccountId, Balance>,
+    // paid on top of each unstake, out of tokens held by this contract
+    pub reward: Balance,
+}
+
+#[near_bindgen]
+impl Contract {
+    #[init]
+    pub fn new(token_account_id: ValidAccountId) -> Self {
+        Contract {
+            token_account_id: token_account_id.into(),
+            staked: HashMap::new(),
+            reward: 0,
+        }
+    }
+
+    pub fn get_staked(&self, account_id: ValidAccountId) -> U128 {
+        self.staked.get::<String>(&account_id.into()).cloned().unwrap_or(0).into()
+    }
+
+    pub fn set_reward(&mut self, reward: U128) {
+        self.reward = reward.into();
+    }
+
+    /// Returns `amount` plus reward to the caller through ft_transfer_call.
+    pub fn unstake(&mut self, amount: U128) -> Promise {
+        let account_id = env::predecessor_account_id();
+        let amount: Balance = amount.into();
+        let staked = self.staked.get_mut(&account_id).expect("ERR_NOT_STAKED");
+        assert!(amount <= *staked, "ERR_NOT_ENOUGH_STAKED");
+        *staked -= amount;
+        log!("Unstake {} token with reward {} to {}", amount, self.reward, account_id);
+        ext_fungible_token::ft_transfer_call(
+            account_id,
+            (amount + self.reward).into(),
+            None,
+            "".to_string(),
+            &self.token_account_id,
+            1,
+            GAS_FOR_FT_TRANSFER_CALL,
+        )
+    }
+}
+
+#[near_bindgen]
+impl FungibleTokenReceiver for Contract {
+    /// msg "refund" refuses the whole amount,
+    /// a number keeps at most that amount and refunds the rest,
+    /// otherwise the whole amount is staked.
+    /// A JSON msg stakes for its `account_id` instead of the sender,
+    /// keeping at most its `limit` if given.
+    fn ft_on_transfer(
+        &mut self,
+        sender_id: ValidAccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        assert_eq!(env::predecessor_account_id(), self.token_account_id, "ERR_WRONG_TOKEN");
+        let amount: Balance = amount.into();
+        let (account_id, limit) = match serde_json::from_str::<Value>(&msg) {
+            Ok(Value::Object(msg)) => (
+                msg["account_id"].as_str().expect("ERR_NO_ACCOUNT_ID").to_string(),
+                msg.get("limit").and_then(|limit| limit.as_str()).map(|limit| limit.to_string()),
+            ),
+            _ => (sender_id.into(), Some(msg)),
+        };
+        let keep = match limit.as_deref() {
+            Some("refund") => 0,
+            Some(limit) => limit.parse::<Balance>().map_or(amount, |limit| std::cmp::min(limit, amount)),
+            None => amount,
+        };
+        *self.staked.entry(account_id.clone()).or_insert(0) += keep;
+        log!("Stake {} token for {}", keep, account_id);
+        PromiseOrValue::Value((amount - keep).into())
+    }
+}
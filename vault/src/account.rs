@@ -1,15 +1,39 @@
 use near_contract_standards::fungible_token::core_impl::ext_fungible_token;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 
-use near_sdk::json_types::{WrappedBalance};
+use near_sdk::json_types::{Base58CryptoHash, ValidAccountId, WrappedBalance};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json;
 use near_sdk::{
-    env, ext_contract, is_promise_success, log, near_bindgen, AccountId, Balance, PromiseOrValue,
+    env, ext_contract, is_promise_success, log, near_bindgen, AccountId, Balance, Promise,
+    PromiseOrValue, PromiseResult, StorageUsage,
 };
 // use std::cmp::Ordering;
 
+use crate::events::emit_renounced;
 use crate::utils::*;
 use crate::*;
 
+/// Context of a beneficiary for bookkeeping, all optional.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Default)]
+#[serde(crate = "near_sdk::serde")]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+pub struct AccountMetadata {
+    pub label: Option<String>,
+    pub cost_center: Option<String>,
+    pub role: Option<String>,
+    // hash of an off-chain record, such as encrypted notes
+    pub external_ref: Option<Base58CryptoHash>,
+}
+
+impl AccountMetadata {
+    pub(crate) fn assert_valid(&self) {
+        for text in [&self.label, &self.cost_center, &self.role].iter().filter_map(|text| text.as_ref()) {
+            require(text.len() <= MAX_METADATA_LEN, VaultError::MetadataTooLong);
+        }
+    }
+}
+
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct Account {
     pub account_id: AccountId,
@@ -18,60 +42,274 @@ pub struct Account {
     pub release_rounds: u32,
     pub last_claim_round: u32,
     pub release_per_round: Balance,
+    // if set, rounds end on this day of each month instead of release_interval
+    pub release_day: Option<u8>,
+    // NEAR staked for the storage of this record
+    pub storage_balance: Balance,
+    // beneficiary opts out of keeper distribution
+    pub push_disabled: bool,
+    // tokens returned by receivers of claim_and_call, claimable again
+    pub refunded_balance: Balance,
+    // cut from the schedule by clawback or termination
+    pub forfeited_balance: Balance,
+    // set by termination, no round completes after it
+    pub end_timestamp: Option<TimestampSec>,
+    pub metadata: AccountMetadata,
+    // claim transfers sent but not resolved yet
+    pub pending_claims: u32,
+    // given by owner with the last clawback
+    pub clawback_reason: Option<String>,
 }
 
 impl Account {
-    pub fn unclaimed_amount(&self, cur_ts: u64) -> u128 {
-        if self.last_claim_round >= self.release_rounds {
-            return 0_u128;
-        }
+    pub fn interval(&self) -> Interval {
+        Interval::from_parts(self.release_interval, self.release_day)
+    }
 
-        let cur_round = if cur_ts > to_nano(self.start_timestamp) {
-            ((cur_ts - to_nano(self.start_timestamp))
-                / to_nano(self.release_interval)) as u32
-        } else {
-            0
-        };
+    pub fn unclaimed_rounds(&self, cur_ts: u64) -> u32 {
+        self.released_rounds(cur_ts)
+            .checked_sub(self.last_claim_round)
+            .or_fail(VaultError::ClaimedOverReleased)
+    }
 
-        let times = if cur_round >= self.release_rounds {
-            self.release_rounds - self.last_claim_round
-        } else {
-            cur_round - self.last_claim_round
-        };
+    /// Rounds released by `cur_ts`, claimed or not.
+    pub fn released_rounds(&self, cur_ts: u64) -> u32 {
+        vault_schedule::claimable_rounds_at(
+            self.start_timestamp,
+            self.interval(),
+            self.release_rounds,
+            0,
+            cur_ts,
+        )
+    }
 
-        let amount = self.release_per_round * times as u128;
+    pub fn unclaimed_amount(&self, cur_ts: u64) -> u128 {
+        self.release_per_round
+            .mul_or_fail(u128::from(self.unclaimed_rounds(cur_ts)))
+            .add_or_fail(self.refunded_balance)
+    }
+
+    /// Drops the last `rounds` of the schedule, returns the amount they carried.
+    pub fn forfeit_rounds(&mut self, rounds: u32) -> Balance {
+        let forfeited = self.release_per_round.mul_or_fail(u128::from(rounds));
+        self.release_rounds = self.release_rounds.sub_or_fail(rounds);
+        self.forfeited_balance = self.forfeited_balance.add_or_fail(forfeited);
+        forfeited
+    }
 
-        amount
+    /// Bytes this record takes in contract state, including its key in `accounts`.
+    pub fn storage_usage(&self) -> StorageUsage {
+        let key_len = self.account_id.try_to_vec().unwrap().len();
+        let value_len = self.try_to_vec().unwrap().len();
+        (key_len + value_len) as StorageUsage
     }
 }
 
 #[near_bindgen]
 impl Contract {
 
+    /// Claim unlocked balance to the caller's wallet.
+    /// If the caller attaches deposit or owner funds the registration reserve,
+    /// the caller is registered to the token contract before transfer when needed,
+    /// unused deposit is refunded.
+    #[payable]
     pub fn claim(&mut self) -> PromiseOrValue<bool> {
         let account_id = env::predecessor_account_id();
+        let deposit = env::attached_deposit();
+
+        let (amount, rounds) = self.internal_claim(&account_id);
+        if amount == 0 {
+            if deposit > 0 {
+                Promise::new(account_id).transfer(deposit);
+            }
+            return PromiseOrValue::Value(true);
+        }
+
+        self.internal_send_claim(account_id, amount, rounds, deposit).into()
+    }
 
-        let (_, global_unlocked) = self.cur_round_and_total_unlock();
-        let liquid_balance = global_unlocked - self.claimed_balance;
+    /// Claim unlocked balance into `receiver_id` through `ft_transfer_call` with `msg`,
+    /// e.g. to stake it right away. The receiver sees the vault as sender, so it must be
+    /// whitelisted by owner as crediting the account named in `msg`, which must be
+    /// a JSON object with the caller as `account_id`.
+    /// Any portion not used by the receiver is credited back to caller's unclaimed amount.
+    pub fn claim_and_call(&mut self, receiver_id: ValidAccountId, msg: String) -> PromiseOrValue<bool> {
+        let account_id = env::predecessor_account_id();
+        require(self.claim_receivers.contains(receiver_id.as_ref()), VaultError::NotClaimReceiver);
+        assert_claim_msg(&msg, &account_id);
 
-        let account = self.accounts.get(&account_id).expect("Account not exist in this contract");
-        let amount = account.unclaimed_amount(env::block_timestamp());
+        let (amount, _) = self.internal_claim(&account_id);
         if amount == 0 {
             return PromiseOrValue::Value(true);
         }
 
-        assert!(
-            amount <= liquid_balance,
-            "The claim amount beyonds liquidity"
-        );
+        ext_fungible_token::ft_transfer_call(
+            receiver_id.into(),
+            amount.into(),
+            Some(format!(
+                "Claiming unlocked {} balance of {} from {}",
+                amount,
+                account_id,
+                env::current_account_id()
+            )),
+            msg,
+            &self.token_account_id,
+            ONE_YOCTO,
+            GAS_FOR_FT_TRANSFER_CALL,
+        )
+        .then(ext_self::after_ft_transfer_call(
+            account_id,
+            amount.into(),
+            &env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_AFTER_FT_TRANSFER,
+        ))
+        .into()
+    }
+
+    /// Owner whitelists a contract for `claim_and_call`, returns false if it already is.
+    pub fn add_claim_receiver(&mut self, receiver_id: ValidAccountId) -> bool {
+        self.assert_owner();
+        self.claim_receivers.insert(receiver_id.into())
+    }
+
+    pub fn remove_claim_receiver(&mut self, receiver_id: ValidAccountId) -> bool {
+        self.assert_owner();
+        self.claim_receivers.remove(receiver_id.as_ref())
+    }
+
+    /// Beneficiary gives up its grant, the account is removed.
+    /// Rounds not released yet are forfeited, so are released but unclaimed ones
+    /// unless `claim_vested`, in which case they are claimed first like `claim`,
+    /// with attached deposit paying for registration. The account then ends now
+    /// and is removed once the transfer succeeds, on failure the claim stays to retry.
+    /// Forfeited tokens stay in the liquid balance.
+    #[payable]
+    pub fn renounce(&mut self, account_id: ValidAccountId, claim_vested: bool) -> PromiseOrValue<bool> {
+        let account_id: AccountId = account_id.into();
+        require(env::predecessor_account_id() == account_id, VaultError::NotAllowed);
+        let account = self.accounts.get(&account_id).or_fail(VaultError::AccountNotExist);
+        let cur_ts = env::block_timestamp();
+        let unvested = account
+            .release_per_round
+            .mul_or_fail(u128::from(account.release_rounds.sub_or_fail(account.released_rounds(cur_ts))));
+        let vested = account.unclaimed_amount(cur_ts);
+        let mut deposit = env::attached_deposit();
+
+        let (amount, rounds) = if claim_vested {
+            self.internal_claim(&account_id)
+        } else {
+            (0, 0)
+        };
+        let forfeited = unvested.add_or_fail(vested).sub_or_fail(amount);
+        log!("Account {} renounced, forfeited {}, claimed {}", account_id, forfeited, amount);
+        emit_renounced(&account_id, forfeited, amount);
+        if amount == 0 {
+            self.internal_remove_account(account_id.clone());
+            if deposit > 0 {
+                Promise::new(account_id).transfer(deposit);
+            }
+            return PromiseOrValue::Value(true);
+        }
+
+        // ends like a termination, so after_ft_transfer collects it
+        let account = self.accounts.get_mut(&account_id).or_fail(VaultError::AccountNotExist);
+        let prev_usage = account.storage_usage();
+        account.forfeit_rounds(account.release_rounds.sub_or_fail(account.last_claim_round));
+        account.end_timestamp = Some(match account.end_timestamp {
+            Some(end) => std::cmp::min(end, cur_sec()),
+            None => cur_sec(),
+        });
+        // growth of the record is staked from attached deposit first
+        let growth = Balance::from(account.storage_usage().saturating_sub(prev_usage))
+            .mul_or_fail(env::storage_byte_cost());
+        let staked = std::cmp::min(growth, deposit);
+        self.storage_pool = self.storage_pool.add_or_fail(staked);
+        deposit = deposit.sub_or_fail(staked);
+        self.internal_restake_storage(&account_id, prev_usage);
+        self.internal_send_claim(account_id, amount, rounds, deposit).into()
+    }
+}
+
+
+/// Fails unless `msg` is a JSON object naming `account_id` for the receiver to credit.
+fn assert_claim_msg(msg: &str, account_id: &str) {
+    let named = serde_json::from_str::<serde_json::Value>(msg)
+        .ok()
+        .and_then(|value| value["account_id"].as_str().map(|named| named == account_id));
+    require(named == Some(true), VaultError::InvalidClaimMsg);
+}
+
+impl Contract {
+
+    /// Move unclaimed amount of the account into claimed balance,
+    /// returns the amount that should be transferred and the rounds it covers.
+    pub(crate) fn internal_claim(&mut self, account_id: &AccountId) -> (Balance, u32) {
+        let liquid_balance = self.cur_liquid_balance();
 
-        let times = (amount / account.release_per_round) as u32;
-        let mut account = self.accounts.get_mut(&account_id).unwrap();
+        let account = self.accounts.get(account_id).or_fail(VaultError::AccountNotExist);
+        let rounds = account.unclaimed_rounds(env::block_timestamp());
+        let amount = account.unclaimed_amount(env::block_timestamp());
+        if amount == 0 {
+            return (0, 0);
+        }
 
-        self.claimed_balance += amount;
-        account.last_claim_round += times;
+        require(amount <= liquid_balance, VaultError::ClaimBeyondLiquidity);
+
+        let account = self.accounts.get_mut(account_id).or_fail(VaultError::AccountNotExist);
+
+        self.claimed_balance = self.claimed_balance.add_or_fail(amount);
+        self.in_flight_balance = self.in_flight_balance.add_or_fail(amount);
+        account.last_claim_round = account.last_claim_round.add_or_fail(rounds);
+        account.refunded_balance = 0;
+        account.pending_claims = account.pending_claims.add_or_fail(1);
+        (amount, rounds)
+    }
+
+    /// Transfer claimed amount to the account,
+    /// checking its registration on token contract first if there is NEAR to pay for it.
+    pub(crate) fn internal_send_claim(
+        &self,
+        account_id: AccountId,
+        amount: Balance,
+        rounds: u32,
+        deposit: Balance,
+    ) -> Promise {
+        if deposit == 0 && self.registration_reserve == 0 {
+            return self.internal_transfer_claim(None, account_id, amount, rounds);
+        }
+        ext_storage_management::storage_balance_of(
+            account_id.clone(),
+            &self.token_account_id,
+            NO_DEPOSIT,
+            GAS_FOR_STORAGE_VIEW,
+        )
+        .and(ext_storage_management::storage_balance_bounds(
+            &self.token_account_id,
+            NO_DEPOSIT,
+            GAS_FOR_STORAGE_VIEW,
+        ))
+        .then(ext_self::after_storage_balance_of(
+            account_id,
+            amount.into(),
+            rounds,
+            deposit.into(),
+            &env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_AFTER_STORAGE_BALANCE_OF,
+        ))
+    }
 
-        ext_fungible_token::ft_transfer(
+    /// Transfer claimed amount with rollback on failure,
+    /// after the registration promise if given.
+    pub(crate) fn internal_transfer_claim(
+        &self,
+        registration: Option<Promise>,
+        account_id: AccountId,
+        amount: Balance,
+        rounds: u32,
+    ) -> Promise {
+        let transfer = ext_fungible_token::ft_transfer(
             account_id.clone(),
             amount.into(),
             Some(format!(
@@ -82,15 +320,19 @@ impl Contract {
             &self.token_account_id,
             ONE_YOCTO,
             GAS_FOR_FT_TRANSFER,
-        )
+        );
+        match registration {
+            Some(registration) => registration.then(transfer),
+            None => transfer,
+        }
         .then(ext_self::after_ft_transfer(
             account_id,
             amount.into(),
+            rounds,
             &env::current_account_id(),
             NO_DEPOSIT,
             GAS_FOR_AFTER_FT_TRANSFER,
         ))
-        .into()
     }
 }
 
@@ -98,60 +340,117 @@ impl Contract {
 impl Contract {
 
     pub fn internal_remove_account(&mut self, account_id: AccountId) -> bool {
-        if let Some(_) = self.accounts.remove(&account_id) {
+        if let Some(account) = self.accounts.remove(&account_id) {
+            self.storage_pool = self.storage_pool.add_or_fail(account.storage_balance);
+            self.account_transfers.remove(&account_id);
             true
         } else {
             false
         }
     }
 
+    /// Removes a terminated account once nothing is left to claim.
+    pub(crate) fn internal_collect_account(&mut self, account_id: &AccountId) -> bool {
+        let collectable = match self.accounts.get(account_id) {
+            Some(account) => {
+                account.end_timestamp.is_some()
+                    && account.last_claim_round == account.release_rounds
+                    && account.refunded_balance == 0
+            }
+            None => false,
+        };
+        if collectable {
+            self.internal_remove_account(account_id.clone());
+            log!("Account {} fully claimed and removed", account_id);
+        }
+        collectable
+    }
+
     pub fn internal_add_account(
         &mut self, 
         account_id: AccountId,
         start_timestamp: TimestampSec,
-        release_interval: TimestampSec,
+        interval: Interval,
         release_rounds: u32,
         release_per_round: Balance,
+        metadata: AccountMetadata,
     ) -> bool {
+        metadata.assert_valid();
+        require(
+            release_rounds > 0 && interval != Interval::Seconds(0),
+            VaultError::InvalidSchedule,
+        );
         if self.accounts.contains_key(&account_id) {
             false
         } else {
-            let account = Account {
+            let (release_interval, release_day) = match interval {
+                Interval::Seconds(release_interval) => (release_interval, None),
+                Interval::MonthDay(release_day) => (0, Some(release_day)),
+            };
+            let mut account = Account {
                 account_id: account_id.clone(),
                 start_timestamp,
                 release_interval,
                 release_rounds,
                 last_claim_round: 0_u32,
                 release_per_round,
+                release_day,
+                storage_balance: 0,
+                push_disabled: false,
+                refunded_balance: 0,
+                forfeited_balance: 0,
+                end_timestamp: None,
+                metadata,
+                pending_claims: 0,
+                clawback_reason: None,
             };
+            account.storage_balance = self.internal_stake_storage(account.storage_usage());
             self.accounts.insert(account_id, account);
             true
         }
     }
 }
 
+#[ext_contract(ext_storage_management)]
+trait TokenStorageManagement {
+    fn storage_deposit(&mut self, account_id: Option<AccountId>, registration_only: Option<bool>);
+    fn storage_balance_of(&self, account_id: AccountId);
+    fn storage_balance_bounds(&self);
+}
+
 #[ext_contract(ext_self)]
 trait AccountClaimCallbacks {
-    fn after_ft_transfer(&mut self, account_id: AccountId, amount: WrappedBalance) -> bool;
+    fn after_ft_transfer(&mut self, account_id: AccountId, amount: WrappedBalance, rounds: u32) -> bool;
+    fn after_ft_transfer_call(&mut self, account_id: AccountId, amount: WrappedBalance) -> bool;
+    fn after_storage_balance_of(&mut self, account_id: AccountId, amount: WrappedBalance, rounds: u32, deposit: WrappedBalance) -> PromiseOrValue<bool>;
+    fn after_storage_deposit(&mut self, account_id: AccountId, from_reserve: WrappedBalance, from_deposit: WrappedBalance) -> bool;
 }
 
 trait AccountClaimCallbacks {
-    fn after_ft_transfer(&mut self, account_id: AccountId, amount: WrappedBalance) -> bool;
+    fn after_ft_transfer(&mut self, account_id: AccountId, amount: WrappedBalance, rounds: u32) -> bool;
+    fn after_ft_transfer_call(&mut self, account_id: AccountId, amount: WrappedBalance) -> bool;
+    fn after_storage_balance_of(&mut self, account_id: AccountId, amount: WrappedBalance, rounds: u32, deposit: WrappedBalance) -> PromiseOrValue<bool>;
+    fn after_storage_deposit(&mut self, account_id: AccountId, from_reserve: WrappedBalance, from_deposit: WrappedBalance) -> bool;
 }
 
 #[near_bindgen]
 impl AccountClaimCallbacks for Contract {
     #[private]
-    fn after_ft_transfer(&mut self, account_id: AccountId, amount: WrappedBalance) -> bool {
+    fn after_ft_transfer(&mut self, account_id: AccountId, amount: WrappedBalance, rounds: u32) -> bool {
+        self.in_flight_balance = self.in_flight_balance.sub_or_fail(amount.0);
+        if let Some(account) = self.accounts.get_mut(&account_id) {
+            account.pending_claims = account.pending_claims.saturating_sub(1);
+        }
         let promise_success = is_promise_success();
         if !promise_success {
-            let mut account = self
-                .accounts
-                .get_mut(&account_id)
-                .expect("The claim is not found");
-            let times = (amount.0 / account.release_per_round) as u32;
-            account.last_claim_round -= times;
-            self.claimed_balance -= amount.0;
+            // if the account has been removed, the amount goes back to liquidity
+            if let Some(account) = self.accounts.get_mut(&account_id) {
+                account.last_claim_round = account.last_claim_round.sub_or_fail(rounds);
+                account.refunded_balance = account
+                    .refunded_balance
+                    .add_or_fail(amount.0.sub_or_fail(account.release_per_round.mul_or_fail(u128::from(rounds))));
+            }
+            self.claimed_balance = self.claimed_balance.sub_or_fail(amount.0);
             log!(
                 "Account claim failed and rollback, account is {}, balance is {}",
                 account_id,
@@ -163,7 +462,365 @@ impl AccountClaimCallbacks for Contract {
                 account_id,
                 amount.0
             );
+            self.internal_collect_account(&account_id);
+        }
+        promise_success
+    }
+
+    /// Credit the portion not used by receiver back to the account,
+    /// nothing is used if the transfer call failed.
+    /// If the account has been removed, the portion goes back to liquidity.
+    #[private]
+    fn after_ft_transfer_call(&mut self, account_id: AccountId, amount: WrappedBalance) -> bool {
+        self.in_flight_balance = self.in_flight_balance.sub_or_fail(amount.0);
+        if let Some(account) = self.accounts.get_mut(&account_id) {
+            account.pending_claims = account.pending_claims.saturating_sub(1);
+        }
+        let used = match env::promise_result(0) {
+            PromiseResult::Successful(value) => serde_json::from_slice::<WrappedBalance>(&value)
+                .map(|used| std::cmp::min(used.0, amount.0))
+                .unwrap_or(amount.0),
+            _ => 0,
+        };
+        let refunded = amount.0.sub_or_fail(used);
+        if refunded > 0 {
+            self.claimed_balance = self.claimed_balance.sub_or_fail(refunded);
+            if let Some(account) = self.accounts.get_mut(&account_id) {
+                account.refunded_balance = account.refunded_balance.add_or_fail(refunded);
+            }
+            log!(
+                "Account claim and call refunded, account is {}, balance is {}",
+                account_id,
+                refunded
+            );
+        }
+        log!(
+            "Account claim and call used, account is {}, balance is {}",
+            account_id,
+            used
+        );
+        self.internal_collect_account(&account_id);
+        used > 0
+    }
+
+    /// Register the account to token contract if it isn't,
+    /// the fee is paid from attached deposit of claim first, then from registration reserve.
+    /// If neither could cover it, just try the transfer.
+    #[private]
+    fn after_storage_balance_of(&mut self, account_id: AccountId, amount: WrappedBalance, rounds: u32, deposit: WrappedBalance) -> PromiseOrValue<bool> {
+        let registered = match env::promise_result(0) {
+            PromiseResult::Successful(value) => serde_json::from_slice::<Option<serde_json::Value>>(&value)
+                .map(|balance| balance.is_some())
+                .unwrap_or(true),
+            _ => true,
+        };
+        let min_fee = match env::promise_result(1) {
+            PromiseResult::Successful(value) => serde_json::from_slice::<serde_json::Value>(&value)
+                .ok()
+                .and_then(|bounds| bounds["min"].as_str().and_then(|min| min.parse::<Balance>().ok())),
+            _ => None,
+        };
+
+        let mut deposit = deposit.0;
+        let registration = match min_fee {
+            Some(fee) if !registered && fee <= deposit.saturating_add(self.registration_reserve) => {
+                let from_reserve = fee.saturating_sub(deposit);
+                let from_deposit = fee.sub_or_fail(from_reserve);
+                self.registration_reserve = self.registration_reserve.sub_or_fail(from_reserve);
+                deposit = deposit.sub_or_fail(from_deposit);
+                log!(
+                    "Register {} to token contract with fee {}, {} from reserve",
+                    account_id,
+                    fee,
+                    from_reserve
+                );
+                Some(
+                    ext_storage_management::storage_deposit(
+                        Some(account_id.clone()),
+                        Some(true),
+                        &self.token_account_id,
+                        fee,
+                        GAS_FOR_STORAGE_DEPOSIT,
+                    )
+                    .then(ext_self::after_storage_deposit(
+                        account_id.clone(),
+                        from_reserve.into(),
+                        from_deposit.into(),
+                        &env::current_account_id(),
+                        NO_DEPOSIT,
+                        GAS_FOR_AFTER_STORAGE_DEPOSIT,
+                    )),
+                )
+            }
+            _ => None,
+        };
+        if deposit > 0 {
+            Promise::new(account_id.clone()).transfer(deposit);
+        }
+        self.internal_transfer_claim(registration, account_id, amount.0, rounds).into()
+    }
+
+    /// If registration failed, the fee is refunded to the vault,
+    /// so credit it back to the reserve and the account that attached it.
+    #[private]
+    fn after_storage_deposit(&mut self, account_id: AccountId, from_reserve: WrappedBalance, from_deposit: WrappedBalance) -> bool {
+        let promise_success = is_promise_success();
+        if !promise_success {
+            self.registration_reserve = self.registration_reserve.add_or_fail(from_reserve.0);
+            if from_deposit.0 > 0 {
+                Promise::new(account_id.clone()).transfer(from_deposit.0);
+            }
+            log!(
+                "Register {} to token contract failed, {} back to reserve",
+                account_id,
+                from_reserve.0
+            );
         }
         promise_success
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::get_logs;
+
+    use super::*;
+    use crate::test_utils::*;
+
+    fn account<'a>(contract: &'a Contract, account_id: &str) -> &'a Account {
+        contract.accounts.get(account_id).unwrap()
+    }
+
+    #[test]
+    fn rounds_are_whole() {
+        let contract = setup();
+        let alice = account(&contract, "alice");
+        assert_eq!(alice.unclaimed_amount(to_nano(100)), 0);
+        assert_eq!(alice.unclaimed_amount(to_nano(109)), 0);
+        assert_eq!(alice.unclaimed_amount(to_nano(110)), 50);
+        assert_eq!(alice.unclaimed_rounds(to_nano(155)), 5);
+        assert_eq!(alice.unclaimed_amount(to_nano(155)), 250);
+        assert_eq!(alice.unclaimed_amount(to_nano(1000)), 500);
+    }
+
+    #[test]
+    fn claim_within_round() {
+        let mut contract = setup();
+        set_context("alice", 155, 0);
+        contract.claim();
+        assert_eq!(account(&contract, "alice").last_claim_round, 5);
+        assert_eq!(contract.claimed_balance, 250);
+        assert_eq!(contract.in_flight_balance, 250);
+
+        set_context("alice", 159, 0);
+        assert_eq!(contract.internal_claim(&"alice".to_string()), (0, 0));
+        set_context("alice", 160, 0);
+        assert_eq!(contract.internal_claim(&"alice".to_string()), (50, 1));
+        assert_eq!(contract.claimed_balance, 300);
+    }
+
+    #[test]
+    fn past_start() {
+        let mut contract = setup();
+        set_context("owner", 1000, NEAR);
+        contract.add_account(id("bob"), 100, 10, 10, U128(20), None);
+        assert_eq!(account(&contract, "bob").unclaimed_amount(to_nano(1000)), 200);
+        set_context("bob", 1000, 0);
+        assert_eq!(contract.internal_claim(&"bob".to_string()), (200, 10));
+        assert_eq!(account(&contract, "bob").unclaimed_amount(to_nano(2000)), 0);
+    }
+
+    #[test]
+    fn zero_interval() {
+        let mut contract = setup();
+        set_context("owner", 150, NEAR);
+        assert_eq!(
+            failure(|| contract.add_account(id("bob"), 200, 0, 5, U128(10), None)),
+            VaultError::InvalidSchedule
+        );
+        assert_eq!(
+            failure(|| contract.add_account(id("bob"), 200, 10, 0, U128(10), None)),
+            VaultError::InvalidSchedule
+        );
+        assert!(contract.accounts.get("bob").is_none());
+    }
+
+    #[test]
+    fn claim_rollback() {
+        let mut contract = setup();
+        set_context("alice", 155, 0);
+        contract.claim();
+        set_context_with_results("vault", 155, 0, vec![PromiseResult::Failed]);
+        assert!(!contract.after_ft_transfer("alice".to_string(), U128(250), 5));
+        let alice = account(&contract, "alice");
+        assert_eq!(alice.last_claim_round, 0);
+        assert_eq!(alice.refunded_balance, 0);
+        assert_eq!(alice.unclaimed_amount(to_nano(155)), 250);
+        assert_eq!(contract.claimed_balance, 0);
+        assert_eq!(contract.in_flight_balance, 0);
+
+        set_context("alice", 155, 0);
+        contract.claim();
+        set_context_with_results("vault", 155, 0, vec![PromiseResult::Successful(vec![])]);
+        assert!(contract.after_ft_transfer("alice".to_string(), U128(250), 5));
+        assert_eq!(account(&contract, "alice").last_claim_round, 5);
+        assert_eq!(contract.claimed_balance, 250);
+        assert_eq!(contract.in_flight_balance, 0);
+    }
+
+    #[test]
+    fn claim_rollback_keeps_refund() {
+        let mut contract = setup();
+        contract.accounts.get_mut("alice").unwrap().refunded_balance = 30;
+        set_context("alice", 155, 0);
+        assert_eq!(contract.internal_claim(&"alice".to_string()), (280, 5));
+        assert_eq!(account(&contract, "alice").refunded_balance, 0);
+        set_context_with_results("vault", 155, 0, vec![PromiseResult::Failed]);
+        contract.after_ft_transfer("alice".to_string(), U128(280), 5);
+        assert_eq!(account(&contract, "alice").refunded_balance, 30);
+        assert_eq!(account(&contract, "alice").unclaimed_amount(to_nano(155)), 280);
+    }
+
+    #[test]
+    fn claim_rollback_of_removed_account() {
+        let mut contract = setup();
+        set_context("alice", 155, 0);
+        contract.claim();
+        contract.internal_remove_account("alice".to_string());
+        set_context_with_results("vault", 155, 0, vec![PromiseResult::Failed]);
+        contract.after_ft_transfer("alice".to_string(), U128(250), 5);
+        assert_eq!(contract.claimed_balance, 0);
+        assert_eq!(contract.cur_liquid_balance(), 500);
+    }
+
+    #[test]
+    fn claim_and_call_refund() {
+        let mut contract = setup();
+        contract.add_claim_receiver(id("staking"));
+        let msg = r#"{"account_id":"alice"}"#.to_string();
+        set_context("alice", 155, 0);
+        contract.claim_and_call(id("staking"), msg.clone());
+        set_context_with_results("vault", 155, 0, vec![PromiseResult::Successful(b"\"100\"".to_vec())]);
+        assert!(contract.after_ft_transfer_call("alice".to_string(), U128(250)));
+        assert_eq!(contract.claimed_balance, 100);
+        assert_eq!(contract.in_flight_balance, 0);
+        let alice = account(&contract, "alice");
+        assert_eq!(alice.last_claim_round, 5);
+        assert_eq!(alice.refunded_balance, 150);
+        assert_eq!(alice.unclaimed_amount(to_nano(155)), 150);
+
+        println!("*** Nothing is used if the transfer call failed");
+        set_context("alice", 155, 0);
+        contract.claim_and_call(id("staking"), msg);
+        set_context_with_results("vault", 155, 0, vec![PromiseResult::Failed]);
+        assert!(!contract.after_ft_transfer_call("alice".to_string(), U128(150)));
+        assert_eq!(contract.claimed_balance, 100);
+        assert_eq!(account(&contract, "alice").refunded_balance, 150);
+    }
+
+    #[test]
+    fn claim_and_call_checks_receiver() {
+        let mut contract = setup();
+        set_context("alice", 155, 0);
+        let msg = r#"{"account_id":"alice","amount":"1"}"#;
+        assert_eq!(
+            failure(|| contract.claim_and_call(id("staking"), msg.to_string())),
+            VaultError::NotClaimReceiver
+        );
+        set_context("owner", 155, 0);
+        assert!(contract.add_claim_receiver(id("staking")));
+        assert!(!contract.add_claim_receiver(id("staking")));
+        assert_eq!(contract.get_claim_receivers(), vec!["staking".to_string()]);
+
+        set_context("alice", 155, 0);
+        for msg in ["", "alice", r#"{"account_id":"bob"}"#, r#"["alice"]"#].iter() {
+            assert_eq!(
+                failure(|| contract.claim_and_call(id("staking"), msg.to_string())),
+                VaultError::InvalidClaimMsg
+            );
+        }
+        contract.claim_and_call(id("staking"), msg.to_string());
+        assert_eq!(contract.in_flight_balance, 250);
+
+        set_context("owner", 155, 0);
+        assert!(contract.remove_claim_receiver(id("staking")));
+        assert!(contract.get_claim_receivers().is_empty());
+    }
+
+    #[test]
+    fn registration_rollback() {
+        let mut contract = setup();
+        set_context("owner", 155, 100);
+        contract.registration_reserve_deposit();
+        set_context("alice", 155, 30);
+        contract.internal_claim(&"alice".to_string());
+        set_context_with_results(
+            "vault",
+            155,
+            0,
+            vec![
+                PromiseResult::Successful(b"null".to_vec()),
+                PromiseResult::Successful(b"{\"min\":\"80\",\"max\":\"80\"}".to_vec()),
+            ],
+        );
+        contract.after_storage_balance_of("alice".to_string(), U128(250), 5, U128(30));
+        assert_eq!(contract.registration_reserve, 50);
+
+        set_context_with_results("vault", 155, 0, vec![PromiseResult::Failed]);
+        assert!(!contract.after_storage_deposit("alice".to_string(), U128(50), U128(30)));
+        assert_eq!(contract.registration_reserve, 100);
+    }
+
+    #[test]
+    fn renounce_removes_on_success() {
+        let mut contract = setup();
+        set_context("alice", 155, NEAR);
+        contract.renounce(id("alice"), true);
+        assert!(get_logs().contains(
+            &r#"EVENT_JSON:{"standard":"vault","version":"1.0.0","event":"renounce","data":[{"account_id":"alice","forfeited_amount":"250","claimed_amount":"250"}]}"#.to_string()
+        ));
+        let alice = account(&contract, "alice");
+        assert_eq!(alice.release_rounds, 5);
+        assert_eq!(alice.end_timestamp, Some(155));
+        assert_eq!(contract.in_flight_balance, 250);
+
+        println!("*** Failed transfer keeps the vested part to claim again");
+        set_context_with_results("vault", 155, 0, vec![PromiseResult::Failed]);
+        assert!(!contract.after_ft_transfer("alice".to_string(), U128(250), 5));
+        assert_eq!(account(&contract, "alice").unclaimed_amount(to_nano(1000)), 250);
+        assert_eq!(contract.claimed_balance, 0);
+
+        set_context("alice", 1000, 0);
+        contract.claim();
+        set_context_with_results("vault", 1000, 0, vec![PromiseResult::Successful(vec![])]);
+        assert!(contract.after_ft_transfer("alice".to_string(), U128(250), 5));
+        assert!(contract.accounts.get("alice").is_none());
+        assert_eq!(contract.claimed_balance, 250);
+        assert_eq!(contract.cur_liquid_balance(), 750);
+    }
+
+    #[test]
+    fn accept_waits_for_claim_in_flight() {
+        let mut contract = setup();
+        set_context("alice", 155, 0);
+        contract.claim();
+        contract.request_account_transfer(id("bob"));
+        set_context("owner", 155, 0);
+        contract.approve_account_transfer(id("alice"));
+        set_context("bob", 155, 0);
+        assert_eq!(failure(|| contract.accept_account_transfer(id("alice"))), VaultError::ClaimInFlight);
+
+        set_context_with_results("vault", 155, 0, vec![PromiseResult::Failed]);
+        contract.after_ft_transfer("alice".to_string(), U128(250), 5);
+        set_context("bob", 155, 0);
+        contract.accept_account_transfer(id("alice"));
+        let bob = contract.accounts.get("bob").unwrap();
+        assert_eq!(bob.last_claim_round, 0);
+        assert_eq!(bob.unclaimed_amount(to_nano(155)), 250);
+        assert_eq!(
+            get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"vault","version":"1.0.0","event":"account_transfer","data":[{"old_account_id":"alice","new_account_id":"bob","last_claim_round":0}]}"#]
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! Owner takes back rounds of an account's schedule, e.g. for misconduct.
+use crate::*;
+use crate::events::emit_clawed_back;
+use std::convert::TryFrom;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, log, near_bindgen, AccountId, Balance, PromiseOrValue};
+
+/// Which rounds of the schedule are clawed back, always from the last round down.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+pub enum ClawbackMode {
+    /// rounds not released yet
+    UnvestedOnly,
+    /// rounds not claimed yet, released or not
+    IncludeUnclaimed,
+    /// a multiple of release_per_round, from rounds not claimed yet
+    Amount(WrappedBalance),
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Cuts rounds from the end of the account's schedule, adding them to its forfeited balance.
+    /// Forfeited tokens stay in the vault, unless `treasury_id` is given,
+    /// then the released part of them is paid to it, rolling back on failure.
+    /// The part not released yet stays locked until the global schedule releases it.
+    /// Refunded balance was claimed before and is not clawed back.
+    /// `reason` is kept on the account, storage it grows by is staked
+    /// from attached deposit first, then from storage pool.
+    /// A terminated account left with nothing to claim is removed.
+    #[payable]
+    pub fn clawback(
+        &mut self,
+        account_id: ValidAccountId,
+        mode: ClawbackMode,
+        reason: String,
+        treasury_id: Option<ValidAccountId>,
+    ) -> PromiseOrValue<bool> {
+        self.assert_owner();
+        require(reason.len() <= MAX_METADATA_LEN, VaultError::MetadataTooLong);
+        self.storage_pool = self.storage_pool.add_or_fail(env::attached_deposit());
+        let account_id: AccountId = account_id.into();
+        let account = self.accounts.get_mut(&account_id).or_fail(VaultError::AccountNotExist);
+        let prev_usage = account.storage_usage();
+        let released_rounds = account.released_rounds(env::block_timestamp());
+        let unclaimed_rounds = account.release_rounds.sub_or_fail(account.last_claim_round);
+        let rounds = match mode {
+            ClawbackMode::UnvestedOnly => account.release_rounds.sub_or_fail(released_rounds),
+            ClawbackMode::IncludeUnclaimed => unclaimed_rounds,
+            ClawbackMode::Amount(amount) => {
+                require(
+                    account.release_per_round > 0 && amount.0 % account.release_per_round == 0,
+                    VaultError::ClawbackAmountNotInRounds,
+                );
+                u32::try_from(amount.0 / account.release_per_round)
+                    .ok()
+                    .filter(|rounds| *rounds <= unclaimed_rounds)
+                    .or_fail(VaultError::ClawbackAmountOverUnclaimed)
+            }
+        };
+        let forfeited = account.forfeit_rounds(rounds);
+        let vested: Balance = account
+            .release_per_round
+            .mul_or_fail(u128::from(released_rounds.saturating_sub(account.release_rounds)));
+        log!(
+            "Clawback {} from {}, {} of it released, reason: {}",
+            forfeited,
+            account_id,
+            vested,
+            reason
+        );
+        emit_clawed_back(&account_id, forfeited, vested, &reason);
+        account.clawback_reason = Some(reason);
+        self.internal_restake_storage(&account_id, prev_usage);
+        self.internal_collect_account(&account_id);
+
+        match treasury_id {
+            Some(treasury_id) if vested > 0 => {
+                let (liquid_balance, unclaimed_balance) = self.cur_funding_balance();
+                require(
+                    vested.add_or_fail(unclaimed_balance) <= liquid_balance,
+                    VaultError::PaymentBeyondLiquidity,
+                );
+                self.internal_payment(
+                    treasury_id.into(),
+                    vested,
+                    format!("Clawback {} balance of {} from {}", vested, account_id, env::current_account_id()),
+                )
+                .into()
+            }
+            _ => PromiseOrValue::Value(true),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::get_logs;
+
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn reason_is_kept() {
+        let mut contract = setup();
+        set_context("owner", 155, 0);
+        contract.clawback(id("alice"), ClawbackMode::UnvestedOnly, "misconduct".to_string(), None);
+        assert!(get_logs().contains(
+            &r#"EVENT_JSON:{"standard":"vault","version":"1.0.0","event":"clawback","data":[{"account_id":"alice","forfeited_amount":"250","released_amount":"0","reason":"misconduct"}]}"#.to_string()
+        ));
+        let alice = contract.get_account(id("alice")).unwrap();
+        assert_eq!(alice.release_rounds, 5);
+        assert_eq!(alice.clawback_reason, Some("misconduct".to_string()));
+        assert_eq!(
+            failure(|| contract.clawback(id("alice"), ClawbackMode::UnvestedOnly, "x".repeat(65), None)),
+            VaultError::MetadataTooLong
+        );
+    }
+
+    #[test]
+    fn terminated_account_is_collected() {
+        let mut contract = setup();
+        set_context("owner", 155, 0);
+        contract.terminate_account(id("alice"), 150);
+        let storage_pool = contract.storage_pool;
+        contract.clawback(id("alice"), ClawbackMode::IncludeUnclaimed, "misconduct".to_string(), None);
+        assert!(contract.accounts.get("alice").is_none());
+        assert!(contract.storage_pool > storage_pool);
+        assert_eq!(contract.get_stats().unclaimed_balance.0, 0);
+    }
+}
@@ -0,0 +1,300 @@
+use crate::*;
+use near_contract_standards::fungible_token::core_impl::ext_fungible_token;
+use near_sdk::json_types::{ValidAccountId, WrappedBalance};
+use near_sdk::serde_json;
+use std::collections::hash_map::Entry;
+use near_sdk::{
+    env, ext_contract, is_promise_success, log, near_bindgen, AccountId, Balance, Promise,
+    PromiseResult,
+};
+
+/// Interface expected from delegation targets.
+/// Recalled tokens should come back through `ft_transfer_call` to the vault,
+/// so that principal and yield are accounted in `ft_on_transfer`.
+#[ext_contract(ext_delegation_target)]
+trait DelegationTarget {
+    fn unstake(&mut self, amount: WrappedBalance);
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn add_delegation_target(&mut self, contract_id: ValidAccountId) -> bool {
+        self.assert_owner();
+        let contract_id: AccountId = contract_id.into();
+        match self.delegations.entry(contract_id) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert(0);
+                true
+            }
+        }
+    }
+
+    /// Only targets without outstanding principal can be removed.
+    pub fn remove_delegation_target(&mut self, contract_id: ValidAccountId) -> bool {
+        self.assert_owner();
+        let contract_id: AccountId = contract_id.into();
+        match self.delegations.get(&contract_id) {
+            Some(principal) => {
+                require(*principal == 0, VaultError::DelegationNotRecalled);
+                self.delegations.remove(&contract_id);
+                self.recalled.remove(&contract_id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn set_delegation_limit(&mut self, delegation_limit_bps: u32) {
+        self.assert_owner();
+        require(delegation_limit_bps <= MAX_BPS, VaultError::InvalidBps);
+        self.delegation_limit_bps = delegation_limit_bps;
+    }
+
+    /// Delegate locked tokens to a whitelisted contract through `ft_transfer_call` with `msg`.
+    /// Total principal can't exceed `delegation_limit_bps` of current locked balance.
+    pub fn delegate(&mut self, contract_id: ValidAccountId, amount: WrappedBalance, msg: String) -> Promise {
+        self.assert_owner();
+        let contract_id: AccountId = contract_id.into();
+        let amount: Balance = amount.into();
+        require(amount > 0, VaultError::ZeroAmount);
+        let (_, global_unlocked) = self.cur_round_and_total_unlock();
+        let locked_balance = self.total_balance.sub_or_fail(global_unlocked);
+        let delegated_balance = self.delegated_balance.add_or_fail(amount);
+        require(
+            U256::from(delegated_balance) * U256::from(MAX_BPS)
+                <= U256::from(locked_balance) * U256::from(self.delegation_limit_bps),
+            VaultError::DelegationExceedsLimit,
+        );
+        let principal = self.delegations.get_mut(&contract_id).or_fail(VaultError::NotDelegationTarget);
+        *principal = principal.add_or_fail(amount);
+        self.delegated_balance = delegated_balance;
+        self.in_flight_balance = self.in_flight_balance.add_or_fail(amount);
+
+        ext_fungible_token::ft_transfer_call(
+            contract_id.clone(),
+            amount.into(),
+            Some(format!(
+                "Delegate {} locked balance from {}",
+                amount,
+                env::current_account_id()
+            )),
+            msg,
+            &self.token_account_id,
+            ONE_YOCTO,
+            GAS_FOR_FT_TRANSFER_CALL,
+        )
+        .then(ext_delegation::after_delegate(
+            contract_id,
+            amount.into(),
+            &env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_AFTER_FT_TRANSFER,
+        ))
+    }
+
+    /// Ask a target to return `amount` of principal not recalled yet.
+    /// Owner can recall any time, others only the part of delegated principal
+    /// exceeding locked balance that isn't on its way back yet,
+    /// so released tokens always stay withdrawable.
+    pub fn recall_delegation(&mut self, contract_id: ValidAccountId, amount: WrappedBalance) -> Promise {
+        let contract_id: AccountId = contract_id.into();
+        let amount: Balance = amount.into();
+        let principal = *self.delegations.get(&contract_id).or_fail(VaultError::NotDelegationTarget);
+        let recalled = self.recalled.get(&contract_id).copied().unwrap_or(0);
+        require(amount <= principal.saturating_sub(recalled), VaultError::ExceedsPrincipal);
+        if env::predecessor_account_id() != self.owner_id {
+            let (_, global_unlocked) = self.cur_round_and_total_unlock();
+            let total_recalled = self.recalled.values().fold(0, |sum: Balance, recalled| sum.add_or_fail(*recalled));
+            let over_delegated = self
+                .delegated_balance
+                .saturating_sub(self.total_balance.sub_or_fail(global_unlocked))
+                .saturating_sub(total_recalled);
+            require(amount <= over_delegated, VaultError::NotAllowed);
+        }
+        self.recalled.insert(contract_id.clone(), recalled.add_or_fail(amount));
+        log!("Recall {} delegated to {}", amount, contract_id);
+        ext_delegation_target::unstake(amount.into(), &contract_id, NO_DEPOSIT, GAS_FOR_RECALL).then(
+            ext_delegation::after_recall(
+                contract_id,
+                amount.into(),
+                &env::current_account_id(),
+                NO_DEPOSIT,
+                GAS_FOR_AFTER_RECALL,
+            ),
+        )
+    }
+
+    /// Owner sends delegation yield out of the vault.
+    pub fn withdraw_delegation_yield(&mut self, receiver_id: ValidAccountId, amount: WrappedBalance) -> Promise {
+        self.assert_owner();
+        let receiver_id: AccountId = receiver_id.into();
+        let amount: Balance = amount.into();
+        require(amount <= self.delegation_yield, VaultError::ExceedsYield);
+        self.delegation_yield = self.delegation_yield.sub_or_fail(amount);
+        self.in_flight_balance = self.in_flight_balance.add_or_fail(amount);
+
+        ext_fungible_token::ft_transfer(
+            receiver_id.clone(),
+            amount.into(),
+            Some(format!(
+                "Withdraw {} delegation yield from {}",
+                amount,
+                env::current_account_id()
+            )),
+            &self.token_account_id,
+            ONE_YOCTO,
+            GAS_FOR_FT_TRANSFER,
+        )
+        .then(ext_delegation::after_yield_transfer(
+            receiver_id,
+            amount.into(),
+            &env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_AFTER_FT_TRANSFER,
+        ))
+    }
+}
+
+impl Contract {
+    /// Tokens returned by a delegation target repay its principal first,
+    /// the rest is yield.
+    pub(crate) fn internal_return_delegation(&mut self, contract_id: &AccountId, amount: Balance) {
+        let principal = self.delegations.get_mut(contract_id).or_fail(VaultError::NotDelegationTarget);
+        let repaid = std::cmp::min(*principal, amount);
+        let earned = amount.sub_or_fail(repaid);
+        *principal = principal.sub_or_fail(repaid);
+        self.delegated_balance = self.delegated_balance.sub_or_fail(repaid);
+        self.internal_clear_recalled(contract_id, repaid);
+        self.delegation_yield = self.delegation_yield.add_or_fail(earned);
+        log!(
+            "Delegation returned from {}, principal {}, yield {}",
+            contract_id,
+            repaid,
+            earned
+        );
+    }
+
+    /// `amount` of principal recalled from the target isn't on its way back any more.
+    fn internal_clear_recalled(&mut self, contract_id: &AccountId, amount: Balance) {
+        if let Some(recalled) = self.recalled.get_mut(contract_id) {
+            *recalled = recalled.saturating_sub(amount);
+            if *recalled == 0 {
+                self.recalled.remove(contract_id);
+            }
+        }
+    }
+}
+
+#[ext_contract(ext_delegation)]
+trait DelegationCallbacks {
+    fn after_delegate(&mut self, contract_id: AccountId, amount: WrappedBalance) -> bool;
+    fn after_yield_transfer(&mut self, receiver_id: AccountId, amount: WrappedBalance) -> bool;
+    fn after_recall(&mut self, contract_id: AccountId, amount: WrappedBalance) -> bool;
+}
+
+#[allow(dead_code)]
+trait DelegationCallbacks {
+    fn after_delegate(&mut self, contract_id: AccountId, amount: WrappedBalance) -> bool;
+    fn after_yield_transfer(&mut self, receiver_id: AccountId, amount: WrappedBalance) -> bool;
+    fn after_recall(&mut self, contract_id: AccountId, amount: WrappedBalance) -> bool;
+}
+
+#[near_bindgen]
+impl DelegationCallbacks for Contract {
+    /// Principal not used by the target, or all if the transfer call failed, is taken back.
+    #[private]
+    fn after_delegate(&mut self, contract_id: AccountId, amount: WrappedBalance) -> bool {
+        self.in_flight_balance = self.in_flight_balance.sub_or_fail(amount.0);
+        let used = match env::promise_result(0) {
+            PromiseResult::Successful(value) => serde_json::from_slice::<WrappedBalance>(&value)
+                .map(|used| std::cmp::min(used.0, amount.0))
+                .unwrap_or(amount.0),
+            _ => 0,
+        };
+        let refunded = amount.0.sub_or_fail(used);
+        if refunded > 0 {
+            if let Some(principal) = self.delegations.get_mut(&contract_id) {
+                *principal = principal.sub_or_fail(refunded);
+            }
+            self.delegated_balance = self.delegated_balance.sub_or_fail(refunded);
+        }
+        log!(
+            "Delegate to {}, used {}, refunded {}",
+            contract_id,
+            used,
+            refunded
+        );
+        used > 0
+    }
+
+    #[private]
+    fn after_yield_transfer(&mut self, receiver_id: AccountId, amount: WrappedBalance) -> bool {
+        self.in_flight_balance = self.in_flight_balance.sub_or_fail(amount.0);
+        let promise_success = is_promise_success();
+        if !promise_success {
+            self.delegation_yield = self.delegation_yield.add_or_fail(amount.0);
+            log!(
+                "Yield withdraw failed and rollback, account is {}, balance is {}",
+                receiver_id,
+                amount.0
+            );
+        }
+        promise_success
+    }
+
+    /// If the target failed to unstake, nothing is coming back, so it can be recalled again.
+    #[private]
+    fn after_recall(&mut self, contract_id: AccountId, amount: WrappedBalance) -> bool {
+        let promise_success = is_promise_success();
+        if !promise_success {
+            self.internal_clear_recalled(&contract_id, amount.0);
+            log!("Recall {} delegated to {} failed", amount.0, contract_id);
+        }
+        promise_success
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::json_types::U128;
+    use near_sdk::PromiseResult;
+
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn recall_once() {
+        let mut contract = setup();
+        set_context("owner", 100, 0);
+        contract.add_delegation_target(id("staking"));
+        contract.set_delegation_limit(5000);
+        contract.delegate(id("staking"), U128(500), String::new());
+
+        println!("*** Over delegated by 100 once 600 is released");
+        set_context("keeper", 160, 0);
+        assert_eq!(failure(|| contract.recall_delegation(id("staking"), U128(110))), VaultError::NotAllowed);
+        contract.recall_delegation(id("staking"), U128(100));
+        assert_eq!(contract.recalled.get("staking"), Some(&100));
+        assert_eq!(failure(|| contract.recall_delegation(id("staking"), U128(1))), VaultError::NotAllowed);
+
+        println!("*** Failed unstake can be recalled again");
+        set_context_with_results("vault", 160, 0, vec![PromiseResult::Failed]);
+        assert!(!contract.after_recall("staking".to_string(), U128(100)));
+        assert!(contract.recalled.is_empty());
+        set_context("keeper", 160, 0);
+        contract.recall_delegation(id("staking"), U128(100));
+
+        println!("*** Owner recalls only principal not recalled yet");
+        set_context("owner", 160, 0);
+        assert_eq!(failure(|| contract.recall_delegation(id("staking"), U128(401))), VaultError::ExceedsPrincipal);
+        contract.recall_delegation(id("staking"), U128(400));
+
+        println!("*** Returns clear what was recalled");
+        contract.internal_return_delegation(&"staking".to_string(), 120);
+        assert_eq!(contract.recalled.get("staking"), Some(&380));
+        contract.internal_return_delegation(&"staking".to_string(), 400);
+        assert!(contract.recalled.is_empty());
+        assert_eq!(contract.delegation_yield, 20);
+    }
+}
@@ -0,0 +1,144 @@
+use crate::*;
+use std::convert::TryFrom;
+use near_sdk::json_types::WrappedBalance;
+use near_sdk::serde_json;
+use near_sdk::{env, ext_contract, log, near_bindgen, AccountId, Gas, PromiseResult};
+
+#[near_bindgen]
+impl Contract {
+    /// Anyone can push claimable balance to accounts in `[from_index, from_index + limit)`
+    /// of the accounts sorted by id, skipping those who disabled push.
+    /// Stops early when prepaid gas runs low, returns the index to continue from.
+    /// If `keeper_reward` is set, the caller gets it for each transfer that succeeds,
+    /// when liquidity allows.
+    pub fn distribute(&mut self, from_index: u64, limit: u64) -> u64 {
+        let mut account_ids: Vec<AccountId> = self.accounts.keys().cloned().collect();
+        account_ids.sort();
+        let from_index = usize::try_from(from_index).ok().or_fail(VaultError::Overflow);
+        let limit = usize::try_from(limit).ok().or_fail(VaultError::Overflow);
+        let to_index = std::cmp::min(from_index.saturating_add(limit), account_ids.len());
+
+        let with_reward = self.keeper_reward > 0;
+        let gas_per_account = self.gas_for_send_claim(with_reward);
+        let mut distributed = 0_u32;
+        let mut index = from_index;
+        while index < to_index {
+            if env::prepaid_gas() - env::used_gas() < gas_per_account {
+                break;
+            }
+            let account_id = &account_ids[index];
+            index += 1;
+
+            let account = self.accounts.get(account_id).or_fail(VaultError::AccountNotExist);
+            if account.push_disabled {
+                continue;
+            }
+            let amount = account.unclaimed_amount(env::block_timestamp());
+            if amount == 0 || amount > self.cur_liquid_balance() {
+                continue;
+            }
+            let (amount, rounds) = self.internal_claim(account_id);
+            let transfer = self.internal_send_claim(account_id.clone(), amount, rounds, 0);
+            if with_reward {
+                transfer.then(ext_keeper::after_distribute_transfer(
+                    env::predecessor_account_id(),
+                    &env::current_account_id(),
+                    NO_DEPOSIT,
+                    GAS_FOR_KEEPER_REWARD,
+                ));
+            }
+            distributed += 1;
+        }
+
+        log!("Distributed to {} accounts, next index is {}", distributed, index);
+        index as u64
+    }
+
+    /// Beneficiary opts out of, or back into, keeper distribution.
+    pub fn set_push_disabled(&mut self, push_disabled: bool) {
+        let account_id = env::predecessor_account_id();
+        let account = self.accounts.get_mut(&account_id).or_fail(VaultError::AccountNotExist);
+        account.push_disabled = push_disabled;
+    }
+
+    pub fn set_keeper_reward(&mut self, keeper_reward: WrappedBalance) {
+        self.assert_owner();
+        self.keeper_reward = keeper_reward.into();
+    }
+}
+
+impl Contract {
+    fn gas_for_send_claim(&self, with_reward: bool) -> Gas {
+        let gas_for_reward = if with_reward { GAS_FOR_KEEPER_REWARD } else { 0 };
+        if self.registration_reserve > 0 {
+            2 * GAS_FOR_STORAGE_VIEW + GAS_FOR_AFTER_STORAGE_BALANCE_OF + GAS_FOR_DISTRIBUTE_ACCOUNT + gas_for_reward
+        } else {
+            GAS_FOR_FT_TRANSFER + GAS_FOR_AFTER_FT_TRANSFER + GAS_FOR_DISTRIBUTE_ACCOUNT + gas_for_reward
+        }
+    }
+}
+
+#[ext_contract(ext_keeper)]
+trait KeeperCallbacks {
+    fn after_distribute_transfer(&mut self, keeper_id: AccountId) -> bool;
+}
+
+#[allow(dead_code)]
+trait KeeperCallbacks {
+    fn after_distribute_transfer(&mut self, keeper_id: AccountId) -> bool;
+}
+
+#[near_bindgen]
+impl KeeperCallbacks for Contract {
+    /// Pays keeper reward once the distributed transfer succeeded, when liquidity allows.
+    #[private]
+    fn after_distribute_transfer(&mut self, keeper_id: AccountId) -> bool {
+        let transferred = match env::promise_result(0) {
+            PromiseResult::Successful(value) => serde_json::from_slice::<bool>(&value).unwrap_or(false),
+            _ => false,
+        };
+        let reward = self.keeper_reward;
+        if !transferred || reward == 0 {
+            return false;
+        }
+        let (liquid_balance, unclaimed_balance) = self.cur_funding_balance();
+        if reward.add_or_fail(unclaimed_balance) > liquid_balance {
+            return false;
+        }
+        self.internal_payment(
+            keeper_id,
+            reward,
+            format!(
+                "Keeper reward {} balance from {}",
+                reward,
+                env::current_account_id()
+            ),
+        );
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::PromiseResult;
+
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn keeper_reward_on_success() {
+        let mut contract = setup();
+        contract.keeper_reward = 1;
+        set_context("keeper", 155, 0);
+        contract.distribute(0, 10);
+        assert_eq!(contract.in_flight_balance, 250);
+        set_context_with_results("vault", 155, 0, vec![PromiseResult::Successful(b"false".to_vec())]);
+        assert!(!contract.after_distribute_transfer("keeper".to_string()));
+        assert_eq!(contract.in_flight_balance, 250);
+
+        set_context_with_results("vault", 155, 0, vec![PromiseResult::Successful(b"true".to_vec())]);
+        assert!(contract.after_distribute_transfer("keeper".to_string()));
+        assert_eq!(contract.claimed_balance, 251);
+        assert_eq!(contract.in_flight_balance, 251);
+    }
+}
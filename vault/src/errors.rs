@@ -0,0 +1,591 @@
+//! Errors the vault fails with. Each has a stable `ERR_*` code,
+//! which is the whole panic message, so clients can match on it.
+use std::fmt;
+
+use near_sdk::env;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VaultError {
+    NotAllowed,
+    NotInitialized,
+    UnknownState,
+    UnknownStateVersion,
+    UnknownUpgradeState,
+    InvariantViolated,
+    // violations listed by check_invariants
+    TrancheSum,
+    ClaimedOverTotal,
+    UnlockOverflow,
+    AccountKey,
+    ClaimRoundOverReleaseRounds,
+    RefundOverClaimed,
+    AccountClaimsOverClaimed,
+    DelegationSum,
+    TransferWithoutAccount,
+    AccountNotExist,
+    AccountExists,
+    MetadataTooLong,
+    InvalidReleaseDay,
+    InvalidSchedule,
+    AlreadyTerminated,
+    EndBeforeLastClaim,
+    ClawbackAmountNotInRounds,
+    ClawbackAmountOverUnclaimed,
+    ClaimBeyondLiquidity,
+    // claim_and_call to a contract not whitelisted
+    NotClaimReceiver,
+    // claim_and_call msg doesn't name the caller as account_id
+    InvalidClaimMsg,
+    PaymentBeyondLiquidity,
+    // claimed balance beyond released one, e.g. a payment got ahead of the schedule
+    ClaimedOverUnlocked,
+    // account claimed rounds not released yet
+    ClaimedOverReleased,
+    Overflow,
+    Underflow,
+    TimestampOverflow,
+    ZeroAmount,
+    InvalidBps,
+    DelegationExceedsLimit,
+    DelegationNotRecalled,
+    NotDelegationTarget,
+    ExceedsPrincipal,
+    ExceedsYield,
+    IllegalToken,
+    ScheduleEnded,
+    NoTranche,
+    InvalidTranche,
+    TrancheOverlap,
+    InsufficientStorage,
+    InsufficientReserve,
+    NoAccountTransfer,
+    NotApproved,
+    // a claim transfer of the account hasn't resolved yet
+    ClaimInFlight,
+    NoStagedCode,
+    NoCode,
+    CodeHashMismatch,
+    // the running code hash was recorded already
+    CodeHashKnown,
+    UpgradeDelay,
+    InvalidRange,
+    TooManyPoints,
+    TooManyAccounts,
+}
+
+impl VaultError {
+    pub const ALL: [VaultError; 58] = [
+        VaultError::NotAllowed,
+        VaultError::NotInitialized,
+        VaultError::UnknownState,
+        VaultError::UnknownStateVersion,
+        VaultError::UnknownUpgradeState,
+        VaultError::InvariantViolated,
+        VaultError::TrancheSum,
+        VaultError::ClaimedOverTotal,
+        VaultError::UnlockOverflow,
+        VaultError::AccountKey,
+        VaultError::ClaimRoundOverReleaseRounds,
+        VaultError::RefundOverClaimed,
+        VaultError::AccountClaimsOverClaimed,
+        VaultError::DelegationSum,
+        VaultError::TransferWithoutAccount,
+        VaultError::AccountNotExist,
+        VaultError::AccountExists,
+        VaultError::MetadataTooLong,
+        VaultError::InvalidReleaseDay,
+        VaultError::InvalidSchedule,
+        VaultError::AlreadyTerminated,
+        VaultError::EndBeforeLastClaim,
+        VaultError::ClawbackAmountNotInRounds,
+        VaultError::ClawbackAmountOverUnclaimed,
+        VaultError::ClaimBeyondLiquidity,
+        VaultError::NotClaimReceiver,
+        VaultError::InvalidClaimMsg,
+        VaultError::PaymentBeyondLiquidity,
+        VaultError::ClaimedOverUnlocked,
+        VaultError::ClaimedOverReleased,
+        VaultError::Overflow,
+        VaultError::Underflow,
+        VaultError::TimestampOverflow,
+        VaultError::ZeroAmount,
+        VaultError::InvalidBps,
+        VaultError::DelegationExceedsLimit,
+        VaultError::DelegationNotRecalled,
+        VaultError::NotDelegationTarget,
+        VaultError::ExceedsPrincipal,
+        VaultError::ExceedsYield,
+        VaultError::IllegalToken,
+        VaultError::ScheduleEnded,
+        VaultError::NoTranche,
+        VaultError::InvalidTranche,
+        VaultError::TrancheOverlap,
+        VaultError::InsufficientStorage,
+        VaultError::InsufficientReserve,
+        VaultError::NoAccountTransfer,
+        VaultError::NotApproved,
+        VaultError::ClaimInFlight,
+        VaultError::NoStagedCode,
+        VaultError::NoCode,
+        VaultError::CodeHashMismatch,
+        VaultError::CodeHashKnown,
+        VaultError::UpgradeDelay,
+        VaultError::InvalidRange,
+        VaultError::TooManyPoints,
+        VaultError::TooManyAccounts,
+    ];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            VaultError::NotAllowed => "ERR_NOT_ALLOWED",
+            VaultError::NotInitialized => "ERR_NOT_INITIALIZED",
+            VaultError::UnknownState => "ERR_UNKNOWN_STATE",
+            VaultError::UnknownStateVersion => "ERR_UNKNOWN_STATE_VERSION",
+            VaultError::UnknownUpgradeState => "ERR_UNKNOWN_UPGRADE_STATE",
+            VaultError::InvariantViolated => "ERR_INVARIANT_VIOLATED",
+            VaultError::TrancheSum => "ERR_TRANCHE_SUM",
+            VaultError::ClaimedOverTotal => "ERR_CLAIMED_OVER_TOTAL",
+            VaultError::UnlockOverflow => "ERR_UNLOCK_OVERFLOW",
+            VaultError::AccountKey => "ERR_ACCOUNT_KEY",
+            VaultError::ClaimRoundOverReleaseRounds => "ERR_CLAIM_ROUND_OVER_RELEASE_ROUNDS",
+            VaultError::RefundOverClaimed => "ERR_REFUND_OVER_CLAIMED",
+            VaultError::AccountClaimsOverClaimed => "ERR_ACCOUNT_CLAIMS_OVER_CLAIMED",
+            VaultError::DelegationSum => "ERR_DELEGATION_SUM",
+            VaultError::TransferWithoutAccount => "ERR_TRANSFER_WITHOUT_ACCOUNT",
+            VaultError::AccountNotExist => "ERR_ACCOUNT_NOT_EXIST",
+            VaultError::AccountExists => "ERR_ACCOUNT_EXISTS",
+            VaultError::MetadataTooLong => "ERR_METADATA_TOO_LONG",
+            VaultError::InvalidReleaseDay => "ERR_INVALID_RELEASE_DAY",
+            VaultError::InvalidSchedule => "ERR_INVALID_SCHEDULE",
+            VaultError::AlreadyTerminated => "ERR_ALREADY_TERMINATED",
+            VaultError::EndBeforeLastClaim => "ERR_END_BEFORE_LAST_CLAIM",
+            VaultError::ClawbackAmountNotInRounds => "ERR_CLAWBACK_AMOUNT_NOT_IN_ROUNDS",
+            VaultError::ClawbackAmountOverUnclaimed => "ERR_CLAWBACK_AMOUNT_OVER_UNCLAIMED",
+            VaultError::ClaimBeyondLiquidity => "ERR_CLAIM_BEYOND_LIQUIDITY",
+            VaultError::NotClaimReceiver => "ERR_NOT_CLAIM_RECEIVER",
+            VaultError::InvalidClaimMsg => "ERR_INVALID_CLAIM_MSG",
+            VaultError::PaymentBeyondLiquidity => "ERR_PAYMENT_BEYOND_LIQUIDITY",
+            VaultError::ClaimedOverUnlocked => "ERR_CLAIMED_OVER_UNLOCKED",
+            VaultError::ClaimedOverReleased => "ERR_CLAIMED_OVER_RELEASED",
+            VaultError::Overflow => "ERR_OVERFLOW",
+            VaultError::Underflow => "ERR_UNDERFLOW",
+            VaultError::TimestampOverflow => "ERR_TIMESTAMP_OVERFLOW",
+            VaultError::ZeroAmount => "ERR_ZERO_AMOUNT",
+            VaultError::InvalidBps => "ERR_INVALID_BPS",
+            VaultError::DelegationExceedsLimit => "ERR_DELEGATION_EXCEEDS_LIMIT",
+            VaultError::DelegationNotRecalled => "ERR_DELEGATION_NOT_RECALLED",
+            VaultError::NotDelegationTarget => "ERR_NOT_DELEGATION_TARGET",
+            VaultError::ExceedsPrincipal => "ERR_EXCEEDS_PRINCIPAL",
+            VaultError::ExceedsYield => "ERR_EXCEEDS_YIELD",
+            VaultError::IllegalToken => "ERR_ILLEGAL_TOKEN",
+            VaultError::ScheduleEnded => "ERR_SCHEDULE_ENDED",
+            VaultError::NoTranche => "ERR_NO_TRANCHE",
+            VaultError::InvalidTranche => "ERR_INVALID_TRANCHE",
+            VaultError::TrancheOverlap => "ERR_TRANCHE_OVERLAP",
+            VaultError::InsufficientStorage => "ERR_INSUFFICIENT_STORAGE",
+            VaultError::InsufficientReserve => "ERR_INSUFFICIENT_RESERVE",
+            VaultError::NoAccountTransfer => "ERR_NO_ACCOUNT_TRANSFER",
+            VaultError::NotApproved => "ERR_NOT_APPROVED",
+            VaultError::ClaimInFlight => "ERR_CLAIM_IN_FLIGHT",
+            VaultError::NoStagedCode => "ERR_NO_STAGED_CODE",
+            VaultError::NoCode => "ERR_NO_CODE",
+            VaultError::CodeHashMismatch => "ERR_CODE_HASH_MISMATCH",
+            VaultError::CodeHashKnown => "ERR_CODE_HASH_KNOWN",
+            VaultError::UpgradeDelay => "ERR_UPGRADE_DELAY",
+            VaultError::InvalidRange => "ERR_INVALID_RANGE",
+            VaultError::TooManyPoints => "ERR_TOO_MANY_POINTS",
+            VaultError::TooManyAccounts => "ERR_TOO_MANY_ACCOUNTS",
+        }
+    }
+
+    /// The error of a failure message, which is the code optionally followed by `: details`.
+    pub fn from_message(message: &str) -> Option<VaultError> {
+        let code = message.split(':').next().unwrap_or_default();
+        VaultError::ALL.iter().copied().find(|error| error.code() == code)
+    }
+
+    /// Fails the call with the code as message.
+    pub fn panic(self) -> ! {
+        env::panic(self.code().as_bytes())
+    }
+
+    /// Fails the call with the code followed by `details`.
+    pub fn panic_with(self, details: &str) -> ! {
+        env::panic(format!("{}: {}", self.code(), details).as_bytes())
+    }
+}
+
+impl fmt::Display for VaultError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// Fails with `error` unless `condition` holds.
+pub(crate) fn require(condition: bool, error: VaultError) {
+    if !condition {
+        error.panic()
+    }
+}
+
+pub(crate) trait OrFail<T> {
+    /// The value, or fail with `error`.
+    fn or_fail(self, error: VaultError) -> T;
+}
+
+impl<T> OrFail<T> for Option<T> {
+    fn or_fail(self, error: VaultError) -> T {
+        match self {
+            Some(value) => value,
+            None => error.panic(),
+        }
+    }
+}
+
+impl<T, E> OrFail<T> for Result<T, E> {
+    fn or_fail(self, error: VaultError) -> T {
+        match self {
+            Ok(value) => value,
+            Err(_) => error.panic(),
+        }
+    }
+}
+
+/// Integer arithmetic failing with `ERR_OVERFLOW` or `ERR_UNDERFLOW`.
+pub(crate) trait CheckedMath: Sized {
+    fn add_or_fail(self, other: Self) -> Self;
+    fn sub_or_fail(self, other: Self) -> Self;
+    fn mul_or_fail(self, other: Self) -> Self;
+}
+
+macro_rules! impl_checked_math {
+    ($($t:ty),*) => {$(
+        impl CheckedMath for $t {
+            fn add_or_fail(self, other: Self) -> Self {
+                self.checked_add(other).or_fail(VaultError::Overflow)
+            }
+
+            fn sub_or_fail(self, other: Self) -> Self {
+                self.checked_sub(other).or_fail(VaultError::Underflow)
+            }
+
+            fn mul_or_fail(self, other: Self) -> Self {
+                self.checked_mul(other).or_fail(VaultError::Overflow)
+            }
+        }
+    )*};
+}
+
+impl_checked_math!(u32, u64, u128, usize);
+
+#[cfg(test)]
+mod tests {
+    use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+    use near_sdk::borsh::BorshSerialize;
+    use near_sdk::json_types::U128;
+
+    use super::*;
+    use crate::legacy::VersionedContract;
+    use crate::test_utils::*;
+    use crate::upgrade::UpgradeState;
+    use crate::utils::{STATE_KEY, STATE_VERSION_KEY};
+    use crate::{AccountMetadata, ClawbackMode, Contract, Tranche};
+
+    #[test]
+    fn codes() {
+        for (index, error) in VaultError::ALL.iter().enumerate() {
+            assert!(error.code().starts_with("ERR_"));
+            assert_eq!(error.to_string(), error.code());
+            assert_eq!(VaultError::from_message(error.code()), Some(*error));
+            assert_eq!(VaultError::from_message(&format!("{}: details", error)), Some(*error));
+            assert!(VaultError::ALL[index + 1..].iter().all(|other| other.code() != error.code()));
+        }
+        assert_eq!(VaultError::from_message("ERR_NOT_A_VAULT_ERROR"), None);
+        assert_eq!(VaultError::from_message("ERR_OVERFLOW_AND_MORE"), None);
+    }
+
+    #[test]
+    fn checked_math() {
+        set_context("owner", 0, 0);
+        assert_eq!(failure(|| u128::MAX.add_or_fail(1)), VaultError::Overflow);
+        assert_eq!(failure(|| 0_u32.sub_or_fail(1)), VaultError::Underflow);
+        assert_eq!(failure(|| u64::MAX.mul_or_fail(2)), VaultError::Overflow);
+        assert_eq!(3_u128.add_or_fail(4).sub_or_fail(2).mul_or_fail(5), 25);
+    }
+
+    #[test]
+    fn accounts() {
+        let mut contract = setup();
+        set_context("alice", 150, 0);
+        assert_eq!(failure(|| contract.set_owner(id("alice"))), VaultError::NotAllowed);
+        assert_eq!(failure(|| contract.renounce(id("owner"), false)), VaultError::NotAllowed);
+        set_context("bob", 150, 0);
+        assert_eq!(failure(|| contract.claim()), VaultError::AccountNotExist);
+        assert_eq!(failure(|| contract.set_push_disabled(true)), VaultError::AccountNotExist);
+
+        set_context("owner", 150, 0);
+        assert_eq!(
+            failure(|| contract.terminate_account(id("bob"), 150)),
+            VaultError::AccountNotExist
+        );
+        let metadata = AccountMetadata {
+            label: Some("x".repeat(65)),
+            ..Default::default()
+        };
+        assert_eq!(
+            failure(|| contract.update_account_metadata(id("alice"), metadata)),
+            VaultError::MetadataTooLong
+        );
+        assert_eq!(
+            failure(|| contract.add_monthly_account(id("bob"), 100, 0, 10, U128(50), None)),
+            VaultError::InvalidReleaseDay
+        );
+        assert_eq!(
+            failure(|| contract.add_account(id("bob"), 100, 0, 10, U128(50), None)),
+            VaultError::InvalidSchedule
+        );
+        assert_eq!(
+            failure(|| contract.add_monthly_account(id("bob"), 100, 1, 0, U128(50), None)),
+            VaultError::InvalidSchedule
+        );
+        assert_eq!(
+            failure(|| contract.clawback(id("alice"), ClawbackMode::Amount(U128(70)), String::new(), None)),
+            VaultError::ClawbackAmountNotInRounds
+        );
+        assert_eq!(
+            failure(|| contract.clawback(id("alice"), ClawbackMode::Amount(U128(550)), String::new(), None)),
+            VaultError::ClawbackAmountOverUnclaimed
+        );
+
+        set_context("alice", 160, 0);
+        contract.claim();
+        set_context("owner", 160, 0);
+        assert_eq!(
+            failure(|| contract.terminate_account(id("alice"), 120)),
+            VaultError::EndBeforeLastClaim
+        );
+        contract.terminate_account(id("alice"), 180);
+        assert_eq!(
+            failure(|| contract.terminate_account(id("alice"), 190)),
+            VaultError::AlreadyTerminated
+        );
+    }
+
+    #[test]
+    fn liquidity() {
+        let mut contract = setup();
+        set_context("owner", 0, NEAR);
+        contract.add_account(id("bob"), 100, 10, 10, U128(200), None);
+        assert_eq!(failure(|| contract.payment(id("owner"), U128(1))), VaultError::PaymentBeyondLiquidity);
+        set_context("bob", 150, 0);
+        assert_eq!(failure(|| contract.claim()), VaultError::ClaimBeyondLiquidity);
+
+        println!("*** A payment got ahead of the schedule");
+        set_context("owner", 0, 0);
+        contract.claimed_balance = 100;
+        assert_eq!(failure(|| contract.get_stats()), VaultError::ClaimedOverUnlocked);
+        assert_eq!(failure(|| contract.assert_invariants()), VaultError::InvariantViolated);
+        assert_eq!(
+            contract.check_invariants(),
+            vec!["ERR_CLAIMED_OVER_UNLOCKED: claimed 100 unlocked 0".to_string()]
+        );
+        contract.claimed_balance = 0;
+
+        println!("*** Account claimed rounds not released yet");
+        set_context("owner", 110, 0);
+        contract.accounts.get_mut("alice").unwrap().last_claim_round = 3;
+        assert_eq!(failure(|| contract.get_account(id("alice"))), VaultError::ClaimedOverReleased);
+        contract.accounts.get_mut("alice").unwrap().last_claim_round = 0;
+
+        println!("*** Balances out of range");
+        contract.accounts.get_mut("bob").unwrap().release_per_round = u128::MAX;
+        set_context("owner", 150, 0);
+        assert_eq!(failure(|| contract.get_stats()), VaultError::Overflow);
+        contract.accounts.get_mut("bob").unwrap().release_per_round = 200;
+        contract.total_balance = 0;
+        assert_eq!(failure(|| contract.get_stats()), VaultError::Underflow);
+    }
+
+    #[test]
+    fn invariants() {
+        let mut contract = setup();
+        set_context("owner", 150, 0);
+        assert!(contract.check_invariants().is_empty());
+        contract.total_balance = 2000;
+        contract.claimed_balance = 2100;
+        let mut alice = contract.accounts.remove("alice").unwrap();
+        alice.last_claim_round = 11;
+        alice.refunded_balance = 600;
+        contract.accounts.insert("carol".to_string(), alice);
+        contract.delegated_balance = 1;
+        let transfer = crate::transfer::AccountTransfer { new_account_id: "erin".to_string(), approved: false };
+        contract.account_transfers.insert("dave".to_string(), transfer);
+        let violations: Vec<_> = contract
+            .check_invariants()
+            .iter()
+            .map(|violation| VaultError::from_message(violation).unwrap())
+            .collect();
+        assert_eq!(
+            violations,
+            vec![
+                VaultError::TrancheSum,
+                VaultError::ClaimedOverTotal,
+                VaultError::ClaimedOverUnlocked,
+                VaultError::AccountKey,
+                VaultError::ClaimRoundOverReleaseRounds,
+                VaultError::RefundOverClaimed,
+                VaultError::DelegationSum,
+                VaultError::TransferWithoutAccount,
+            ]
+        );
+        contract.claimed_balance = 0;
+        contract.accounts.get_mut("carol").unwrap().refunded_balance = 0;
+        let violation = "ERR_ACCOUNT_CLAIMS_OVER_CLAIMED: accounts 550 claimed 0".to_string();
+        assert!(contract.check_invariants().contains(&violation));
+
+        println!("*** Unlocked tranches beyond u128 are reported");
+        let mut tranche = contract.tranches[0].clone();
+        tranche.total_balance = U128(u128::MAX);
+        contract.tranches = vec![tranche.clone(), tranche];
+        set_context("owner", 1000, 0);
+        let violations = contract.check_invariants();
+        assert!(violations.iter().any(|violation| violation.starts_with("ERR_UNLOCK_OVERFLOW: ")));
+        assert!(!violations.iter().any(|violation| violation.starts_with("ERR_CLAIMED_OVER_UNLOCKED")));
+    }
+
+    #[test]
+    fn schedule() {
+        let mut contract = setup();
+        set_context("owner", 150, 0);
+        assert_eq!(failure(|| contract.top_up(U128(0), 0)), VaultError::ZeroAmount);
+        set_context("owner", 1000, 0);
+        assert_eq!(failure(|| contract.top_up(U128(10), 0)), VaultError::ScheduleEnded);
+        set_context("token", 1000, 0);
+        assert_eq!(
+            failure(|| contract.ft_on_transfer(id("owner"), U128(10), "{\"TopUp\":{\"extend_rounds\":0}}".to_string())),
+            VaultError::ScheduleEnded
+        );
+        assert_eq!(
+            failure(|| contract.ft_on_transfer(id("alice"), U128(10), "{\"TopUp\":{\"extend_rounds\":1}}".to_string())),
+            VaultError::NotAllowed
+        );
+        set_context("alice", 1000, 0);
+        assert_eq!(
+            failure(|| contract.ft_on_transfer(id("alice"), U128(10), String::new())),
+            VaultError::IllegalToken
+        );
+
+        let tranche = |start_timestamp, release_rounds| Tranche {
+            start_timestamp,
+            release_interval: 10,
+            release_rounds,
+            total_balance: U128(100),
+            release_day: None,
+        };
+        assert_eq!(
+            failure(|| Contract::new_with_tranches(id("owner"), id("token"), vec![])),
+            VaultError::NoTranche
+        );
+        assert_eq!(
+            failure(|| Contract::new_with_tranches(id("owner"), id("token"), vec![tranche(0, 0)])),
+            VaultError::InvalidTranche
+        );
+        assert_eq!(
+            failure(|| Contract::new_with_tranches(id("owner"), id("token"), vec![tranche(0, 10), tranche(90, 10)])),
+            VaultError::TrancheOverlap
+        );
+    }
+
+    #[test]
+    fn projection() {
+        let contract = setup();
+        assert_eq!(failure(|| contract.get_projection(10, 0, 1)), VaultError::InvalidRange);
+        assert_eq!(failure(|| contract.get_projection(0, 10, 0)), VaultError::InvalidRange);
+        assert_eq!(failure(|| contract.get_projection(0, 500, 1)), VaultError::TooManyPoints);
+        assert_eq!(
+            failure(|| contract.get_projection(0, u64::MAX, u64::MAX / 2)),
+            VaultError::TimestampOverflow
+        );
+    }
+
+    #[test]
+    fn delegations() {
+        let mut contract = setup();
+        set_context("owner", 150, 0);
+        assert_eq!(failure(|| contract.set_delegation_limit(10_001)), VaultError::InvalidBps);
+        assert_eq!(
+            failure(|| contract.delegate(id("staking"), U128(0), String::new())),
+            VaultError::ZeroAmount
+        );
+        assert_eq!(
+            failure(|| contract.recall_delegation(id("staking"), U128(1))),
+            VaultError::NotDelegationTarget
+        );
+        contract.set_delegation_limit(10_000);
+        assert_eq!(
+            failure(|| contract.delegate(id("staking"), U128(1), String::new())),
+            VaultError::NotDelegationTarget
+        );
+        contract.add_delegation_target(id("staking"));
+        assert_eq!(
+            failure(|| contract.delegate(id("staking"), U128(501), String::new())),
+            VaultError::DelegationExceedsLimit
+        );
+        assert_eq!(
+            failure(|| contract.recall_delegation(id("staking"), U128(1))),
+            VaultError::ExceedsPrincipal
+        );
+        assert_eq!(
+            failure(|| contract.withdraw_delegation_yield(id("owner"), U128(1))),
+            VaultError::ExceedsYield
+        );
+        contract.delegate(id("staking"), U128(500), String::new());
+        assert_eq!(
+            failure(|| contract.remove_delegation_target(id("staking"))),
+            VaultError::DelegationNotRecalled
+        );
+    }
+
+    #[test]
+    fn storage_and_transfers() {
+        let mut contract = setup();
+        set_context("owner", 150, 0);
+        assert_eq!(failure(|| contract.storage_pool_withdraw(U128(NEAR))), VaultError::InsufficientStorage);
+        assert_eq!(failure(|| contract.registration_reserve_withdraw(U128(1))), VaultError::InsufficientReserve);
+        assert_eq!(failure(|| contract.approve_account_transfer(id("alice"))), VaultError::NoAccountTransfer);
+
+        set_context("alice", 150, 0);
+        assert_eq!(failure(|| contract.request_account_transfer(id("alice"))), VaultError::AccountExists);
+        contract.request_account_transfer(id("bob"));
+        set_context("carol", 150, 0);
+        assert_eq!(failure(|| contract.accept_account_transfer(id("alice"))), VaultError::NotAllowed);
+        set_context("bob", 150, 0);
+        assert_eq!(failure(|| contract.accept_account_transfer(id("alice"))), VaultError::NotApproved);
+        assert_eq!(failure(|| contract.request_account_transfer(id("carol"))), VaultError::AccountNotExist);
+    }
+
+    #[test]
+    fn state_and_upgrade() {
+        let mut contract = setup();
+        set_context("owner", 150, 0);
+        assert_eq!(failure(|| contract.cancel_staged_code()), VaultError::NoStagedCode);
+        assert_eq!(failure(|| contract.deploy_staged_code([0; 32].into())), VaultError::NoStagedCode);
+
+        let state = UpgradeState {
+            staged_hash: Some([1; 32]),
+            staged_at: env::block_timestamp(),
+            ..Default::default()
+        };
+        env::storage_write(b"UPGRADE", &state.try_to_vec().unwrap());
+        assert_eq!(failure(|| contract.deploy_staged_code([0; 32].into())), VaultError::CodeHashMismatch);
+        assert_eq!(failure(|| contract.deploy_staged_code([1; 32].into())), VaultError::UpgradeDelay);
+        env::storage_write(b"UPGRADE", b"corrupt");
+        assert_eq!(failure(|| contract.get_staged_code_hash()), VaultError::UnknownUpgradeState);
+
+        assert_eq!(failure(VersionedContract::read), VaultError::NotInitialized);
+        env::storage_write(STATE_KEY, b"corrupt");
+        assert_eq!(failure(VersionedContract::read), VaultError::UnknownState);
+        env::storage_write(STATE_VERSION_KEY, &[9]);
+        assert_eq!(failure(VersionedContract::read), VaultError::UnknownStateVersion);
+    }
+}
@@ -0,0 +1,113 @@
+//! Structured events, logged in NEP-297 format as `EVENT_JSON:` followed by
+//! `{"standard": "vault", "version": ..., "event": ..., "data": [...]}`,
+//! so indexers parse them rather than log text.
+use near_sdk::serde::Serialize;
+use near_sdk::serde_json;
+use near_sdk::json_types::U128;
+use near_sdk::{env, AccountId, Balance};
+
+use crate::utils::TimestampSec;
+
+pub const EVENT_STANDARD: &str = "vault";
+pub const EVENT_VERSION: &str = "1.0.0";
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a, T: Serialize> {
+    standard: &'a str,
+    version: &'a str,
+    event: &'a str,
+    data: [T; 1],
+}
+
+fn emit<T: Serialize>(event: &str, data: T) {
+    let log = EventLog {
+        standard: EVENT_STANDARD,
+        version: EVENT_VERSION,
+        event,
+        data: [data],
+    };
+    env::log(format!("EVENT_JSON:{}", serde_json::to_string(&log).unwrap()).as_bytes());
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct AccountTransferred<'a> {
+    old_account_id: &'a AccountId,
+    new_account_id: &'a AccountId,
+    last_claim_round: u32,
+}
+
+/// The account record moved from `old_account_id` to `new_account_id`.
+pub(crate) fn emit_account_transferred(old_account_id: &AccountId, new_account_id: &AccountId, last_claim_round: u32) {
+    emit(
+        "account_transfer",
+        AccountTransferred {
+            old_account_id,
+            new_account_id,
+            last_claim_round,
+        },
+    );
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct Renounced<'a> {
+    account_id: &'a AccountId,
+    forfeited_amount: U128,
+    claimed_amount: U128,
+}
+
+/// The beneficiary gave up `forfeited_amount`, claiming `claimed_amount` of it first.
+pub(crate) fn emit_renounced(account_id: &AccountId, forfeited_amount: Balance, claimed_amount: Balance) {
+    emit(
+        "renounce",
+        Renounced {
+            account_id,
+            forfeited_amount: forfeited_amount.into(),
+            claimed_amount: claimed_amount.into(),
+        },
+    );
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct ClawedBack<'a> {
+    account_id: &'a AccountId,
+    forfeited_amount: U128,
+    released_amount: U128,
+    reason: &'a str,
+}
+
+/// Owner cut `forfeited_amount` from the account, `released_amount` of it released already.
+pub(crate) fn emit_clawed_back(account_id: &AccountId, forfeited_amount: Balance, released_amount: Balance, reason: &str) {
+    emit(
+        "clawback",
+        ClawedBack {
+            account_id,
+            forfeited_amount: forfeited_amount.into(),
+            released_amount: released_amount.into(),
+            reason,
+        },
+    );
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct Terminated<'a> {
+    account_id: &'a AccountId,
+    end_timestamp: TimestampSec,
+    forfeited_amount: U128,
+}
+
+/// Owner ended the account's schedule at `end_timestamp`, cutting `forfeited_amount` after it.
+pub(crate) fn emit_terminated(account_id: &AccountId, end_timestamp: TimestampSec, forfeited_amount: Balance) {
+    emit(
+        "terminate",
+        Terminated {
+            account_id,
+            end_timestamp,
+            forfeited_amount: forfeited_amount.into(),
+        },
+    );
+}
@@ -0,0 +1,106 @@
+//! Consistency checks of the state, run by `migrate` and exposed as a view.
+use crate::*;
+use near_sdk::{env, near_bindgen};
+
+#[near_bindgen]
+impl Contract {
+    /// Violated invariants, each as `CODE: details` of its `VaultError`, empty if consistent.
+    pub fn check_invariants(&self) -> Vec<String> {
+        self.internal_check_invariants()
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_check_invariants(&self) -> Vec<String> {
+        let mut violations = vec![];
+
+        // saturating sums, a corrupt state is reported rather than failing the check
+        let tranche_sum: Balance = self
+            .tranches
+            .iter()
+            .fold(0, |sum, tranche| sum.saturating_add(tranche.total_balance.0));
+        if tranche_sum != self.total_balance {
+            violations.push(format!(
+                "{}: tranches {} total {}",
+                VaultError::TrancheSum, tranche_sum, self.total_balance
+            ));
+        }
+        if self.claimed_balance > self.total_balance {
+            violations.push(format!(
+                "{}: claimed {} total {}",
+                VaultError::ClaimedOverTotal, self.claimed_balance, self.total_balance
+            ));
+        }
+        let cur_ts = env::block_timestamp();
+        let unlocked = self
+            .tranches
+            .iter()
+            .try_fold(0, |sum: Balance, tranche| sum.checked_add(tranche.unlocked(cur_ts)));
+        match unlocked {
+            Some(unlocked) if self.claimed_balance > unlocked => violations.push(format!(
+                "{}: claimed {} unlocked {}",
+                VaultError::ClaimedOverUnlocked, self.claimed_balance, unlocked
+            )),
+            Some(_) => {}
+            None => violations.push(format!(
+                "{}: tranches {} at {}",
+                VaultError::UnlockOverflow,
+                self.tranches.len(),
+                cur_ts
+            )),
+        }
+
+        // claims of removed accounts and payments are in claimed balance too
+        let mut account_claims: Balance = 0;
+        for (account_id, account) in self.accounts.iter() {
+            if *account_id != account.account_id {
+                violations.push(format!("{}: {} {}", VaultError::AccountKey, account_id, account.account_id));
+            }
+            if account.last_claim_round > account.release_rounds {
+                violations.push(format!(
+                    "{}: {} claimed {} rounds {}",
+                    VaultError::ClaimRoundOverReleaseRounds,
+                    account_id,
+                    account.last_claim_round,
+                    account.release_rounds
+                ));
+            }
+            let claimed = account.release_per_round.saturating_mul(u128::from(account.last_claim_round));
+            if account.refunded_balance > claimed {
+                violations.push(format!(
+                    "{}: {} refunded {} claimed {}",
+                    VaultError::RefundOverClaimed, account_id, account.refunded_balance, claimed
+                ));
+            }
+            account_claims = account_claims.saturating_add(claimed.saturating_sub(account.refunded_balance));
+        }
+        if account_claims > self.claimed_balance {
+            violations.push(format!(
+                "{}: accounts {} claimed {}",
+                VaultError::AccountClaimsOverClaimed, account_claims, self.claimed_balance
+            ));
+        }
+
+        let delegation_sum: Balance = self.delegations.values().fold(0, |sum, principal| sum.saturating_add(*principal));
+        if delegation_sum != self.delegated_balance {
+            violations.push(format!(
+                "{}: delegations {} delegated {}",
+                VaultError::DelegationSum, delegation_sum, self.delegated_balance
+            ));
+        }
+        for account_id in self.account_transfers.keys() {
+            if !self.accounts.contains_key(account_id) {
+                violations.push(format!("{}: {}", VaultError::TransferWithoutAccount, account_id));
+            }
+        }
+        violations
+    }
+
+    /// Panics with all violations, if any.
+    pub(crate) fn assert_invariants(&self) {
+        let violations = self.internal_check_invariants();
+        if !violations.is_empty() {
+            VaultError::InvariantViolated.panic_with(&violations.join("; "));
+        }
+    }
+}
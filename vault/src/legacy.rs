@@ -0,0 +1,133 @@
+//! State layouts of released versions, used by `migrate` to upgrade from them.
+//!
+//! To change the layout, add a variant for the new one to `StateVersion` and
+//! `VersionedContract`, keep the previous `Contract` here renamed after its
+//! version, and convert it into the new one with `From`.
+use crate::*;
+
+/// Account layout of 0.0.0 to 0.3.1.
+#[derive(BorshDeserialize)]
+pub struct AccountV030 {
+    pub account_id: AccountId,
+    pub start_timestamp: u32,
+    pub release_interval: u32,
+    pub release_rounds: u32,
+    pub last_claim_round: u32,
+    pub release_per_round: Balance,
+}
+
+/// Contract layout of 0.0.0 to 0.3.1.
+/// 0.0.0 was frozen, only `force_payment` worked, so it never held accounts.
+#[derive(BorshDeserialize)]
+pub struct ContractV030 {
+    pub owner_id: AccountId,
+    pub token_account_id: AccountId,
+    pub total_balance: Balance,
+    pub start_timestamp: u32,
+    pub release_interval: u32,
+    pub release_rounds: u32,
+    pub accounts: HashMap<AccountId, AccountV030>,
+    pub claimed_balance: Balance,
+}
+
+impl From<AccountV030> for Account {
+    fn from(a: AccountV030) -> Self {
+        Account {
+            account_id: a.account_id,
+            start_timestamp: a.start_timestamp.into(),
+            release_interval: a.release_interval.into(),
+            release_rounds: a.release_rounds,
+            last_claim_round: a.last_claim_round,
+            release_per_round: a.release_per_round,
+            release_day: None,
+            // storage of these records was paid by the vault itself
+            storage_balance: 0,
+            push_disabled: false,
+            refunded_balance: 0,
+            forfeited_balance: 0,
+            end_timestamp: None,
+            metadata: AccountMetadata::default(),
+            pending_claims: 0,
+            clawback_reason: None,
+        }
+    }
+}
+
+impl From<ContractV030> for Contract {
+    fn from(c: ContractV030) -> Self {
+        Contract {
+            owner_id: c.owner_id,
+            token_account_id: c.token_account_id,
+            total_balance: c.total_balance,
+            // the single linear release becomes the only tranche
+            tranches: vec![Tranche {
+                start_timestamp: c.start_timestamp.into(),
+                release_interval: c.release_interval.into(),
+                release_rounds: c.release_rounds,
+                total_balance: c.total_balance.into(),
+                release_day: None,
+            }],
+            accounts: c.accounts.into_iter().map(|(k, v)| (k, v.into())).collect(),
+            claimed_balance: c.claimed_balance,
+            storage_pool: 0,
+            registration_reserve: 0,
+            keeper_reward: 0,
+            delegations: HashMap::new(),
+            delegation_limit_bps: 0,
+            delegated_balance: 0,
+            delegation_yield: 0,
+            recalled: HashMap::new(),
+            account_transfers: HashMap::new(),
+            in_flight_balance: 0,
+            claim_receivers: HashSet::new(),
+        }
+    }
+}
+
+/// Stored under `STATE_VERSION_KEY` by `new` and `migrate`.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, PartialEq)]
+pub enum StateVersion {
+    V030,
+    V040,
+}
+
+pub const CURRENT_STATE_VERSION: StateVersion = StateVersion::V040;
+
+/// State in the layout of any released version.
+pub enum VersionedContract {
+    V030(ContractV030),
+    V040(Box<Contract>),
+}
+
+impl VersionedContract {
+    /// Reads the state in the layout of its stored version,
+    /// states without a version are from 0.3.1 or before.
+    pub fn read() -> Self {
+        let data = env::storage_read(STATE_KEY).or_fail(VaultError::NotInitialized);
+        let version = env::storage_read(STATE_VERSION_KEY)
+            .map(|version| StateVersion::try_from_slice(&version).or_fail(VaultError::UnknownStateVersion))
+            .unwrap_or(StateVersion::V030);
+        match version {
+            StateVersion::V030 => VersionedContract::V030(
+                ContractV030::try_from_slice(&data).or_fail(VaultError::UnknownState),
+            ),
+            StateVersion::V040 => VersionedContract::V040(Box::new(
+                Contract::try_from_slice(&data).or_fail(VaultError::UnknownState),
+            )),
+        }
+    }
+
+    /// Converts step by step through every later layout.
+    pub fn into_current(self) -> Contract {
+        match self {
+            VersionedContract::V030(contract) => {
+                VersionedContract::V040(Box::new(contract.into())).into_current()
+            }
+            VersionedContract::V040(contract) => *contract,
+        }
+    }
+}
+
+pub(crate) fn write_state_version() {
+    env::storage_write(STATE_VERSION_KEY, &CURRENT_STATE_VERSION.try_to_vec().unwrap());
+}
@@ -1,18 +1,45 @@
 
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use near_sdk::json_types::{ValidAccountId, WrappedBalance};
 use near_sdk::{
     env, near_bindgen, AccountId, Balance, PanicOnDefault, 
 };
 use account::Account;
 use utils::*;
+use errors::{require, CheckedMath, OrFail};
 // for sim-test
-pub use view::{Stats, AccountOutput};
+pub use view::{Stats, AccountOutput, TrancheOutput, ProjectionPoint, CostCenterOutput};
+pub use account::AccountMetadata;
+pub use errors::VaultError;
+pub use transfer::AccountTransfer;
+pub use clawback::ClawbackMode;
+pub use schedule::Tranche;
+pub use snapshot::{state_bytes, AccountRecord, StateHeader, StateSnapshot, SNAPSHOT_FORMAT};
+// for offline reports to bound points the same way
+pub use utils::MAX_PROJECTION_POINTS;
+// for client to size gas of calls
+pub use utils::{GAS_FOR_AFTER_STORAGE_BALANCE_OF, GAS_FOR_STORAGE_VIEW};
 
 mod account;
+mod clawback;
+mod delegation;
+mod distribute;
+mod errors;
+mod events;
+mod invariants;
+mod legacy;
 mod utils;
 mod owner;
+mod recovery;
+mod schedule;
+mod snapshot;
+mod storage;
+#[cfg(test)]
+mod test_utils;
+mod token_receiver;
+mod transfer;
+mod upgrade;
 mod view;
 
 uint::construct_uint! {
@@ -27,13 +54,42 @@ pub struct Contract {
     pub owner_id: AccountId,
     pub token_account_id: AccountId,
     pub total_balance: Balance,
-    pub start_timestamp: TimestampSec,
-    pub release_interval: TimestampSec,
-    pub release_rounds: u32,
+    // the global release schedule, total_balance is the sum of them
+    pub tranches: Vec<Tranche>,
 
     pub accounts: HashMap<AccountId, Account>,
     pub claimed_balance: Balance,
     // liquid_balance = total - locked - claimed
+
+    // NEAR provided by owner to stake storage of account records
+    pub storage_pool: Balance,
+    // NEAR provided by owner to register beneficiaries to token contract
+    pub registration_reserve: Balance,
+    // token paid to keeper for each account distributed
+    pub keeper_reward: Balance,
+
+    // whitelisted contracts that locked tokens can be delegated to,
+    // with principal delegated to each of them
+    pub delegations: HashMap<AccountId, Balance>,
+    // the max delegated principal, in bps of locked balance
+    pub delegation_limit_bps: u32,
+    // total principal out in delegations
+    pub delegated_balance: Balance,
+    // returned by delegations beyond principal, not part of release schedule
+    pub delegation_yield: Balance,
+    // principal recalled from each target and not returned yet
+    pub recalled: HashMap<AccountId, Balance>,
+
+    // pending wallet rotations, keyed by current account id
+    pub account_transfers: HashMap<AccountId, AccountTransfer>,
+
+    // tokens held for promises not resolved yet,
+    // e.g. moved out of accounting but transfer may roll back
+    pub in_flight_balance: Balance,
+
+    // whitelisted contracts for claim_and_call, known to credit the account named in msg
+    pub claim_receivers: HashSet<AccountId>,
+
 }
 
 #[near_bindgen]
@@ -48,48 +104,209 @@ impl Contract {
         release_interval: TimestampSec,
         release_rounds: u32,
     ) -> Self {
+        let tranches = vec![Tranche {
+            start_timestamp,
+            release_interval,
+            release_rounds,
+            total_balance,
+            release_day: None,
+        }];
+        Self::assert_tranches(&tranches);
+        legacy::write_state_version();
         Self {
             accounts: HashMap::new(),
             owner_id: owner_id.into(),
             token_account_id: token_account_id.into(),
             total_balance: total_balance.into(),
-            start_timestamp,
-            release_interval,
-            release_rounds,
+            tranches,
             claimed_balance: 0,
+            storage_pool: 0,
+            registration_reserve: 0,
+            keeper_reward: 0,
+            delegations: HashMap::new(),
+            delegation_limit_bps: 0,
+            delegated_balance: 0,
+            delegation_yield: 0,
+            recalled: HashMap::new(),
+            account_transfers: HashMap::new(),
+            in_flight_balance: 0,
+            claim_receivers: HashSet::new(),
         }
     }
 
+    /// Init with a schedule of sequential tranches.
+    #[init]
+    pub fn new_with_tranches(
+        owner_id: ValidAccountId,
+        token_account_id: ValidAccountId,
+        tranches: Vec<Tranche>,
+    ) -> Self {
+        Self::assert_tranches(&tranches);
+        let first = tranches[0].clone();
+        let mut contract = Self::new(
+            owner_id,
+            token_account_id,
+            first.total_balance,
+            first.start_timestamp,
+            first.release_interval,
+            first.release_rounds,
+        );
+        contract.total_balance = tranches
+            .iter()
+            .fold(0, |total: Balance, tranche| total.add_or_fail(tranche.total_balance.0));
+        contract.tranches = tranches;
+        contract
+    }
+
 }
 
 
 impl Contract {
     fn cur_round_and_total_unlock(&self) -> (u32, u128) {
-        let cur_round = if env::block_timestamp() > to_nano(self.start_timestamp) {
-            ((env::block_timestamp() - to_nano(self.start_timestamp))
-                / to_nano(self.release_interval)) as u32
-        } else {
-            0
-        };
-
-        let unlocked = if cur_round < self.release_rounds {
-            (U256::from(self.total_balance) * U256::from(cur_round)
-                / U256::from(self.release_rounds))
-            .as_u128()
-        } else {
-            self.total_balance
-        };
+        self.round_and_total_unlock_at(env::block_timestamp())
+    }
 
+    /// Global round and released amount at the given timestamp in nano seconds.
+    fn round_and_total_unlock_at(&self, cur_ts: u64) -> (u32, u128) {
+        let cur_round = self.cur_round(cur_ts);
+        let unlocked = self
+            .tranches
+            .iter()
+            .fold(0, |unlocked: Balance, tranche| unlocked.add_or_fail(tranche.unlocked(cur_ts)));
         (cur_round, unlocked)
     }
 
+    fn cur_liquid_balance(&self) -> u128 {
+        self.liquid_balance_at(env::block_timestamp())
+    }
+
+    /// Released but undistributed balance that the vault can pay at the given timestamp,
+    /// delegated tokens beyond locked balance are excluded until recalled.
+    fn liquid_balance_at(&self, cur_ts: u64) -> u128 {
+        let (_, global_unlocked) = self.round_and_total_unlock_at(cur_ts);
+        global_unlocked
+            .checked_sub(self.claimed_balance)
+            .or_fail(VaultError::ClaimedOverUnlocked)
+            .saturating_sub(self.over_delegated(global_unlocked))
+    }
+
+    /// Delegated principal beyond the locked balance.
+    fn over_delegated(&self, global_unlocked: u128) -> u128 {
+        self.delegated_balance
+            .saturating_sub(self.total_balance.sub_or_fail(global_unlocked))
+    }
+
     fn cur_funding_balance(&self) -> (u128, u128) {
-        let (_, global_unlocked) = self.cur_round_and_total_unlock();
-        let liquid_balance = global_unlocked - self.claimed_balance;
+        self.funding_balance_at(env::block_timestamp())
+    }
+
+    /// Liquid balance and unclaimed amount of all accounts at the given timestamp.
+    fn funding_balance_at(&self, cur_ts: u64) -> (u128, u128) {
+        let liquid_balance = self.liquid_balance_at(cur_ts);
         let mut unclaimed = 0_u128;
         for account in self.accounts.values() {
-            unclaimed += account.unclaimed_amount(env::block_timestamp());
+            unclaimed = unclaimed.add_or_fail(account.unclaimed_amount(cur_ts));
         }
         (liquid_balance, unclaimed)
     }
+
+    /// Same as `funding_balance_at` but saturating at 0 instead of failing,
+    /// for projected points before claims and payments already made.
+    fn projected_funding_balance_at(&self, cur_ts: u64) -> (u128, u128) {
+        let (_, global_unlocked) = self.round_and_total_unlock_at(cur_ts);
+        let liquid_balance = global_unlocked
+            .saturating_sub(self.claimed_balance)
+            .saturating_sub(self.over_delegated(global_unlocked));
+        let unclaimed = self.accounts.values().fold(0_u128, |total, account| {
+            let rounds = account.released_rounds(cur_ts).saturating_sub(account.last_claim_round);
+            total.saturating_add(
+                account
+                    .release_per_round
+                    .saturating_mul(u128::from(rounds))
+                    .saturating_add(account.refunded_balance),
+            )
+        });
+        (liquid_balance, unclaimed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::json_types::U128;
+
+    use super::*;
+    use crate::test_utils::*;
+
+    fn new_contract(total_balance: Balance, start_timestamp: TimestampSec, release_interval: TimestampSec, release_rounds: u32) -> Contract {
+        Contract::new(id("owner"), id("token"), U128(total_balance), start_timestamp, release_interval, release_rounds)
+    }
+
+    #[test]
+    fn rounding() {
+        set_context("owner", 0, 0);
+        let contract = new_contract(1000, 100, 10, 3);
+        assert_eq!(contract.round_and_total_unlock_at(to_nano(100)), (0, 0));
+        // within a round nothing more is released
+        assert_eq!(contract.round_and_total_unlock_at(to_nano(119)), (1, 333));
+        assert_eq!(contract.round_and_total_unlock_at(to_nano(120)), (2, 666));
+        assert_eq!(contract.round_and_total_unlock_at(to_nano(130)), (3, 1000));
+        // round counts on after the end, the balance doesn't
+        assert_eq!(contract.round_and_total_unlock_at(to_nano(500)), (40, 1000));
+
+        let contract = new_contract(u128::MAX, 100, 10, 3);
+        assert_eq!(contract.round_and_total_unlock_at(to_nano(110)).1, u128::MAX / 3);
+    }
+
+    #[test]
+    fn past_start() {
+        set_context("owner", 1000, 0);
+        let contract = new_contract(1000, 100, 10, 10);
+        assert_eq!(contract.cur_round_and_total_unlock(), (90, 1000));
+        assert_eq!(contract.cur_liquid_balance(), 1000);
+    }
+
+    #[test]
+    fn invalid_schedule() {
+        set_context("owner", 100, 0);
+        assert_eq!(failure(|| new_contract(1000, 100, 0, 10)), VaultError::InvalidTranche);
+        assert_eq!(failure(|| new_contract(1000, 100, 10, 0)), VaultError::InvalidTranche);
+    }
+
+    #[test]
+    fn tranches() {
+        set_context("owner", 0, 0);
+        let tranche = |start_timestamp, release_rounds, total_balance| Tranche {
+            start_timestamp,
+            release_interval: 10,
+            release_rounds,
+            total_balance: U128(total_balance),
+            release_day: None,
+        };
+        let contract = Contract::new_with_tranches(
+            id("owner"),
+            id("token"),
+            vec![tranche(100, 2, 200), tranche(500, 4, 1000)],
+        );
+        assert_eq!(contract.total_balance, 1200);
+        // rounds of the first tranche stop at its end, the gap adds none
+        assert_eq!(contract.round_and_total_unlock_at(to_nano(300)), (2, 200));
+        assert_eq!(contract.round_and_total_unlock_at(to_nano(520)), (4, 700));
+        assert_eq!(contract.round_and_total_unlock_at(to_nano(600)), (12, 1200));
+    }
+
+    #[test]
+    fn liquidity() {
+        let mut contract = setup();
+        set_context("owner", 150, 0);
+        assert_eq!(contract.cur_funding_balance(), (500, 250));
+
+        contract.claimed_balance = 100;
+        assert_eq!(contract.cur_liquid_balance(), 400);
+
+        println!("*** Delegated beyond locked balance isn't liquid");
+        contract.delegated_balance = 800;
+        assert_eq!(contract.cur_liquid_balance(), 100);
+        contract.delegated_balance = 500;
+        assert_eq!(contract.cur_liquid_balance(), 400);
+    }
 }
@@ -1,8 +1,11 @@
 use crate::*;
+use crate::events::emit_terminated;
+use crate::legacy::{write_state_version, VersionedContract};
 use crate::utils::TimestampSec;
 use near_sdk::json_types::{ValidAccountId, WrappedBalance};
 use near_sdk::{
-    env, ext_contract, is_promise_success, log, near_bindgen, AccountId, Balance, PromiseOrValue,
+    env, ext_contract, is_promise_success, log, near_bindgen, AccountId, Balance, Promise,
+    PromiseOrValue,
 };
 use near_contract_standards::fungible_token::core_impl::ext_fungible_token;
 
@@ -18,6 +21,40 @@ impl Contract {
         self.internal_remove_account(account_id.into())
     }
 
+    /// Stops vesting of the account at `end_timestamp`, which may be in the future.
+    /// Rounds completed by then stay claimable, later ones are forfeited.
+    /// The account is removed once fully claimed, returns true if that's already the case.
+    /// Termination can only be moved earlier. Storage the record grows by is staked
+    /// from attached deposit first, then from storage pool.
+    #[payable]
+    pub fn terminate_account(&mut self, account_id: ValidAccountId, end_timestamp: TimestampSec) -> bool {
+        self.assert_owner();
+        self.storage_pool = self.storage_pool.add_or_fail(env::attached_deposit());
+        let account_id: AccountId = account_id.into();
+        let account = self.accounts.get_mut(&account_id).or_fail(VaultError::AccountNotExist);
+        require(
+            !matches!(account.end_timestamp, Some(end) if end_timestamp > end),
+            VaultError::AlreadyTerminated,
+        );
+        let prev_usage = account.storage_usage();
+        let rounds = account.released_rounds(to_nano(end_timestamp));
+        require(rounds >= account.last_claim_round, VaultError::EndBeforeLastClaim);
+        let forfeited = account.forfeit_rounds(account.release_rounds.sub_or_fail(rounds));
+        account.end_timestamp = Some(end_timestamp);
+        log!(
+            "Account {} terminated at {}, forfeited {}",
+            account_id,
+            end_timestamp,
+            forfeited
+        );
+        emit_terminated(&account_id, end_timestamp, forfeited);
+        self.internal_restake_storage(&account_id, prev_usage);
+        self.internal_collect_account(&account_id)
+    }
+
+    /// Storage of the new record is staked from attached deposit first,
+    /// and the rest from storage pool. Extra deposit goes into the pool.
+    #[payable]
     pub fn add_account(
         &mut self, 
         account_id: ValidAccountId,
@@ -25,76 +62,127 @@ impl Contract {
         release_interval: TimestampSec,
         release_rounds: u32,
         release_per_round: WrappedBalance,
+        metadata: Option<AccountMetadata>,
     ) -> bool {
         self.assert_owner();
+        self.storage_pool = self.storage_pool.add_or_fail(env::attached_deposit());
         self.internal_add_account(
             account_id.into(), 
             start_timestamp, 
-            release_interval, 
+            release_interval.into(),
+            release_rounds,
+            release_per_round.into(),
+            metadata.unwrap_or_default(),
+        )
+    }
+
+    /// Same as `add_account`, but rounds are calendar months
+    /// ending on `release_day` (1 to 31) at 00:00 UTC, or the last day of shorter months.
+    #[payable]
+    pub fn add_monthly_account(
+        &mut self,
+        account_id: ValidAccountId,
+        start_timestamp: TimestampSec,
+        release_day: u8,
+        release_rounds: u32,
+        release_per_round: WrappedBalance,
+        metadata: Option<AccountMetadata>,
+    ) -> bool {
+        self.assert_owner();
+        require(Interval::MonthDay(release_day).is_valid(), VaultError::InvalidReleaseDay);
+        self.storage_pool = self.storage_pool.add_or_fail(env::attached_deposit());
+        self.internal_add_account(
+            account_id.into(),
+            start_timestamp,
+            Interval::MonthDay(release_day),
             release_rounds,
-            release_per_round.into()
+            release_per_round.into(),
+            metadata.unwrap_or_default(),
         )
     }
 
+    /// Replaces the metadata of the account. Storage the record grows by is staked
+    /// from attached deposit first, then from storage pool, what it shrinks by goes to the pool.
+    #[payable]
+    pub fn update_account_metadata(&mut self, account_id: ValidAccountId, metadata: AccountMetadata) {
+        self.assert_owner();
+        self.storage_pool = self.storage_pool.add_or_fail(env::attached_deposit());
+        metadata.assert_valid();
+        let account_id: AccountId = account_id.into();
+        let account = self.accounts.get_mut(&account_id).or_fail(VaultError::AccountNotExist);
+        let prev_usage = account.storage_usage();
+        account.metadata = metadata;
+        self.internal_restake_storage(&account_id, prev_usage);
+        log!("Account {} metadata updated", account_id);
+    }
+
     pub fn payment(&mut self, receiver_id: ValidAccountId, amount: WrappedBalance) -> PromiseOrValue<bool> {
         self.assert_owner();
         let amount: Balance = amount.into();
         let account_id: AccountId = receiver_id.into();
 
         let (liquid_balance, unclaimed_balance) = self.cur_funding_balance();
-        assert!(
-            (amount + unclaimed_balance) <= liquid_balance,
-            "The payment amount beyonds liquidity"
+        require(
+            amount.add_or_fail(unclaimed_balance) <= liquid_balance,
+            VaultError::PaymentBeyondLiquidity,
         );
 
         if amount > 0 {
-            self.claimed_balance += amount;
-
-            ext_fungible_token::ft_transfer(
-                account_id.clone(),
-                amount.into(),
-                Some(format!(
+            self.internal_payment(
+                account_id,
+                amount,
+                format!(
                     "Payment {} balance from {}",
                     amount,
                     env::current_account_id()
-                )),
-                &self.token_account_id,
-                ONE_YOCTO,
-                GAS_FOR_FT_TRANSFER,
+                ),
             )
-            .then(ext_payment::after_payment_transfer(
-                account_id,
-                amount.into(),
-                &env::current_account_id(),
-                NO_DEPOSIT,
-                GAS_FOR_AFTER_FT_TRANSFER,
-            ))
             .into()
         } else {
             PromiseOrValue::Value(true)
         }
     }
 
-    /// Migration function between versions.
-    /// For next version upgrades, change this function.
+    /// Migration function between versions,
+    /// converts the state through every layout after its stored version.
+    /// Fails the upgrade if the result breaks any invariant.
     #[init(ignore_state)]
     #[private]
     pub fn migrate() -> Self {
-        assert_eq!(
-            env::predecessor_account_id(),
-            env::current_account_id(),
-            "ERR_NOT_ALLOWED"
+        require(
+            env::predecessor_account_id() == env::current_account_id(),
+            VaultError::NotAllowed,
         );
-        let contract: Contract = env::state_read().expect("ERR_NOT_INITIALIZED");
+        let contract = VersionedContract::read().into_current();
+        contract.assert_invariants();
+        write_state_version();
         contract
     }
 
+    /// Transfer amount out of liquid balance, rolls back on failure.
+    pub(crate) fn internal_payment(&mut self, account_id: AccountId, amount: Balance, memo: String) -> Promise {
+        self.claimed_balance = self.claimed_balance.add_or_fail(amount);
+        self.in_flight_balance = self.in_flight_balance.add_or_fail(amount);
+
+        ext_fungible_token::ft_transfer(
+            account_id.clone(),
+            amount.into(),
+            Some(memo),
+            &self.token_account_id,
+            ONE_YOCTO,
+            GAS_FOR_FT_TRANSFER,
+        )
+        .then(ext_payment::after_payment_transfer(
+            account_id,
+            amount.into(),
+            &env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_AFTER_FT_TRANSFER,
+        ))
+    }
+
     pub(crate) fn assert_owner(&self) {
-        assert_eq!(
-            env::predecessor_account_id(),
-            self.owner_id,
-            "ERR_NOT_ALLOWED"
-        );
+        require(env::predecessor_account_id() == self.owner_id, VaultError::NotAllowed);
     }
 }
 
@@ -111,9 +199,10 @@ trait AccountPaymentCallbacks {
 impl AccountPaymentCallbacks for Contract {
     #[private]
     fn after_payment_transfer(&mut self, account_id: AccountId, amount: WrappedBalance) -> bool {
+        self.in_flight_balance = self.in_flight_balance.sub_or_fail(amount.0);
         let promise_success = is_promise_success();
         if !promise_success {
-            self.claimed_balance -= amount.0;
+            self.claimed_balance = self.claimed_balance.sub_or_fail(amount.0);
             log!(
                 "Payment failed and rollback, account is {}, balance is {}",
                 account_id,
@@ -130,59 +219,93 @@ impl AccountPaymentCallbacks for Contract {
     }
 }
 
-#[cfg(target_arch = "wasm32")]
-mod upgrade {
-    use near_sdk::env::BLOCKCHAIN_INTERFACE;
-    use near_sdk::Gas;
+#[cfg(test)]
+mod tests {
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::get_logs;
+    use near_sdk::PromiseResult;
 
     use super::*;
+    use crate::test_utils::*;
 
-    const BLOCKCHAIN_INTERFACE_NOT_SET_ERR: &str = "Blockchain interface not set.";
-
-    /// Gas for calling migration call.
-    pub const GAS_FOR_MIGRATE_CALL: Gas = 10_000_000_000_000;
-
-    /// Self upgrade and call migrate, optimizes gas by not loading into memory the code.
-    /// Takes as input non serialized set of bytes of the code.
-    #[no_mangle]
-    pub extern "C" fn upgrade() {
-        env::setup_panic_hook();
-        env::set_blockchain_interface(Box::new(near_blockchain::NearBlockchain {}));
-        let contract: Contract = env::state_read().expect("ERR_CONTRACT_IS_NOT_INITIALIZED");
-        contract.assert_owner();
-        let current_id = env::current_account_id().into_bytes();
-        let method_name = "migrate".as_bytes().to_vec();
-        unsafe {
-            BLOCKCHAIN_INTERFACE.with(|b| {
-                // Load input into register 0.
-                b.borrow()
-                    .as_ref()
-                    .expect(BLOCKCHAIN_INTERFACE_NOT_SET_ERR)
-                    .input(0);
-                let promise_id = b
-                    .borrow()
-                    .as_ref()
-                    .expect(BLOCKCHAIN_INTERFACE_NOT_SET_ERR)
-                    .promise_batch_create(current_id.len() as _, current_id.as_ptr() as _);
-                b.borrow()
-                    .as_ref()
-                    .expect(BLOCKCHAIN_INTERFACE_NOT_SET_ERR)
-                    .promise_batch_action_deploy_contract(promise_id, u64::MAX as _, 0);
-                let attached_gas = env::prepaid_gas() - env::used_gas() - GAS_FOR_MIGRATE_CALL;
-                b.borrow()
-                    .as_ref()
-                    .expect(BLOCKCHAIN_INTERFACE_NOT_SET_ERR)
-                    .promise_batch_action_function_call(
-                        promise_id,
-                        method_name.len() as _,
-                        method_name.as_ptr() as _,
-                        0 as _,
-                        0 as _,
-                        0 as _,
-                        attached_gas,
-                    );
-            });
-        }
+    #[test]
+    fn add_account_stakes_storage() {
+        let mut contract = setup();
+        let staked = contract.accounts.get("alice").unwrap().storage_balance;
+        assert_eq!(
+            staked,
+            Balance::from(contract.accounts.get("alice").unwrap().storage_usage()) * env::storage_byte_cost()
+        );
+        assert_eq!(contract.storage_pool, NEAR - staked);
+
+        println!("*** Existing account is kept, deposit stays in the pool");
+        set_context("owner", 0, NEAR);
+        assert!(!contract.add_account(id("alice"), 0, 1, 1, U128(1), None));
+        assert_eq!(contract.accounts.get("alice").unwrap().release_per_round, 50);
+        assert_eq!(contract.storage_pool, 2 * NEAR - staked);
     }
-}
 
+    #[test]
+    fn terminate() {
+        let mut contract = setup();
+        set_context("owner", 150, 0);
+        assert!(!contract.terminate_account(id("alice"), 175));
+        assert!(get_logs().contains(
+            &r#"EVENT_JSON:{"standard":"vault","version":"1.0.0","event":"terminate","data":[{"account_id":"alice","end_timestamp":175,"forfeited_amount":"150"}]}"#.to_string()
+        ));
+        let alice = contract.accounts.get("alice").unwrap();
+        assert_eq!(alice.release_rounds, 7);
+        assert_eq!(alice.forfeited_balance, 150);
+        assert_eq!(alice.end_timestamp, Some(175));
+        assert_eq!(alice.unclaimed_amount(to_nano(1000)), 350);
+
+        println!("*** Termination in the past collects an account fully claimed");
+        set_context("alice", 150, 0);
+        contract.internal_claim(&"alice".to_string());
+        set_context("owner", 150, 0);
+        assert!(contract.terminate_account(id("alice"), 150));
+        assert!(contract.accounts.get("alice").is_none());
+        assert_eq!(contract.storage_pool, NEAR);
+    }
+
+    #[test]
+    fn payment_rollback() {
+        let mut contract = setup();
+        set_context("owner", 150, 0);
+        contract.payment(id("bob"), U128(200));
+        assert_eq!(contract.claimed_balance, 200);
+        assert_eq!(contract.in_flight_balance, 200);
+        // alice still has 250 to claim out of 500 released
+        assert_eq!(failure(|| contract.payment(id("bob"), U128(51))), VaultError::PaymentBeyondLiquidity);
+
+        set_context_with_results("vault", 150, 0, vec![PromiseResult::Failed]);
+        assert!(!contract.after_payment_transfer("bob".to_string(), U128(200)));
+        assert_eq!(contract.claimed_balance, 0);
+        assert_eq!(contract.in_flight_balance, 0);
+
+        set_context("owner", 150, 0);
+        contract.payment(id("bob"), U128(250));
+        set_context_with_results("vault", 150, 0, vec![PromiseResult::Successful(vec![])]);
+        assert!(contract.after_payment_transfer("bob".to_string(), U128(250)));
+        assert_eq!(contract.claimed_balance, 250);
+        assert_eq!(contract.in_flight_balance, 0);
+    }
+
+    #[test]
+    fn update_metadata_restakes() {
+        let mut contract = setup();
+        let staked = contract.accounts.get("alice").unwrap().storage_balance;
+        let metadata = AccountMetadata {
+            label: Some("Alice".to_string()),
+            ..Default::default()
+        };
+        set_context("owner", 0, 0);
+        contract.update_account_metadata(id("alice"), metadata);
+        let grown = contract.accounts.get("alice").unwrap().storage_balance - staked;
+        assert!(grown > 0);
+        assert_eq!(contract.storage_pool, NEAR - staked - grown);
+        contract.update_account_metadata(id("alice"), AccountMetadata::default());
+        assert_eq!(contract.accounts.get("alice").unwrap().storage_balance, staked);
+        assert_eq!(contract.storage_pool, NEAR - staked);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::*;
+use near_contract_standards::fungible_token::core_impl::ext_fungible_token;
+use near_sdk::json_types::{ValidAccountId, WrappedBalance};
+use near_sdk::serde_json;
+use near_sdk::{
+    env, ext_contract, is_promise_success, log, near_bindgen, AccountId, Balance, Promise,
+    PromiseOrValue, PromiseResult,
+};
+
+#[near_bindgen]
+impl Contract {
+    /// Owner sends tokens the vault holds beyond its accounting to `receiver_id`.
+    /// For the managed token, that's the balance above what the vault still owes,
+    /// for any other token, the whole balance.
+    pub fn recover_excess(&mut self, token_id: ValidAccountId, receiver_id: ValidAccountId) -> Promise {
+        self.assert_owner();
+        let token_id: AccountId = token_id.into();
+        ext_fungible_token::ft_balance_of(
+            env::current_account_id(),
+            &token_id,
+            NO_DEPOSIT,
+            GAS_FOR_BALANCE_VIEW,
+        )
+        .then(ext_recovery::after_recover_balance_of(
+            token_id,
+            receiver_id.into(),
+            &env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_AFTER_BALANCE_OF,
+        ))
+    }
+}
+
+impl Contract {
+    /// Managed tokens the vault should hold: not yet claimed or paid, minus those out in delegations,
+    /// plus delegation yield and those held for unresolved promises.
+    pub(crate) fn expected_token_balance(&self) -> Balance {
+        self.total_balance
+            .sub_or_fail(self.claimed_balance)
+            .add_or_fail(self.delegation_yield)
+            .add_or_fail(self.in_flight_balance)
+            .saturating_sub(self.delegated_balance)
+    }
+
+    /// Managed tokens the vault may hold with recalled principal returned but not accounted yet.
+    fn recovery_expected_balance(&self) -> Balance {
+        self.recalled
+            .values()
+            .fold(self.expected_token_balance(), |expected, recalled| expected.add_or_fail(*recalled))
+    }
+}
+
+#[ext_contract(ext_recovery)]
+trait RecoveryCallbacks {
+    fn after_recover_balance_of(&mut self, token_id: AccountId, receiver_id: AccountId) -> PromiseOrValue<bool>;
+    fn after_recover_transfer(&mut self, token_id: AccountId, receiver_id: AccountId, amount: WrappedBalance) -> bool;
+}
+
+#[allow(dead_code)]
+trait RecoveryCallbacks {
+    fn after_recover_balance_of(&mut self, token_id: AccountId, receiver_id: AccountId) -> PromiseOrValue<bool>;
+    fn after_recover_transfer(&mut self, token_id: AccountId, receiver_id: AccountId, amount: WrappedBalance) -> bool;
+}
+
+#[near_bindgen]
+impl RecoveryCallbacks for Contract {
+    /// The expected balance is taken once the balance is known,
+    /// counting recalled principal as returned, so tokens on the way can't look like excess.
+    #[private]
+    fn after_recover_balance_of(&mut self, token_id: AccountId, receiver_id: AccountId) -> PromiseOrValue<bool> {
+        let balance = match env::promise_result(0) {
+            PromiseResult::Successful(value) => serde_json::from_slice::<WrappedBalance>(&value)
+                .map(|balance| balance.0)
+                .unwrap_or(0),
+            _ => 0,
+        };
+        let expected = if token_id == self.token_account_id {
+            self.recovery_expected_balance()
+        } else {
+            0
+        };
+        let amount = balance.saturating_sub(expected);
+        if amount == 0 {
+            log!("No excess of {} to recover, balance is {}", token_id, balance);
+            return PromiseOrValue::Value(false);
+        }
+        if token_id == self.token_account_id {
+            self.in_flight_balance = self.in_flight_balance.add_or_fail(amount);
+        }
+
+        ext_fungible_token::ft_transfer(
+            receiver_id.clone(),
+            amount.into(),
+            Some(format!(
+                "Recover {} excess balance from {}",
+                amount,
+                env::current_account_id()
+            )),
+            &token_id,
+            ONE_YOCTO,
+            GAS_FOR_FT_TRANSFER,
+        )
+        .then(ext_recovery::after_recover_transfer(
+            token_id,
+            receiver_id,
+            amount.into(),
+            &env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_AFTER_FT_TRANSFER,
+        ))
+        .into()
+    }
+
+    #[private]
+    fn after_recover_transfer(&mut self, token_id: AccountId, receiver_id: AccountId, amount: WrappedBalance) -> bool {
+        if token_id == self.token_account_id {
+            self.in_flight_balance = self.in_flight_balance.sub_or_fail(amount.0);
+        }
+        let promise_success = is_promise_success();
+        log!(
+            "Recover {} of {} to {} {}",
+            amount.0,
+            token_id,
+            receiver_id,
+            if promise_success { "succeed" } else { "failed" }
+        );
+        promise_success
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::PromiseResult;
+
+    use super::*;
+    use crate::test_utils::*;
+
+    fn balance_result(balance: Balance) -> Vec<PromiseResult> {
+        vec![PromiseResult::Successful(serde_json::to_vec(&WrappedBalance::from(balance)).unwrap())]
+    }
+
+    #[test]
+    fn recall_on_the_way_is_no_excess() {
+        let mut contract = setup();
+        contract.delegated_balance = 500;
+        contract.recalled.insert("staking".to_string(), 200);
+
+        println!("*** Recalled principal arrived before being accounted");
+        set_context_with_results("vault", 100, 0, balance_result(700));
+        assert!(matches!(
+            contract.after_recover_balance_of("token".to_string(), "owner".to_string()),
+            PromiseOrValue::Value(false)
+        ));
+
+        println!("*** Only the balance beyond it is recovered");
+        set_context_with_results("vault", 100, 0, balance_result(730));
+        assert!(matches!(
+            contract.after_recover_balance_of("token".to_string(), "owner".to_string()),
+            PromiseOrValue::Promise(_)
+        ));
+        assert_eq!(contract.in_flight_balance, 30);
+    }
+}
@@ -0,0 +1,219 @@
+use crate::*;
+use near_contract_standards::fungible_token::core_impl::ext_fungible_token;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json;
+use near_sdk::{env, ext_contract, log, near_bindgen, Promise, PromiseResult};
+
+/// A linear release over `release_rounds` rounds of `release_interval`,
+/// or of calendar months ending on `release_day` if given,
+/// starting from `start_timestamp`. The global schedule is an ordered,
+/// non-overlapping sequence of them.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+pub struct Tranche {
+    pub start_timestamp: TimestampSec,
+    pub release_interval: TimestampSec,
+    pub release_rounds: u32,
+    pub total_balance: WrappedBalance,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_day: Option<u8>,
+}
+
+impl Tranche {
+    pub fn interval(&self) -> Interval {
+        Interval::from_parts(self.release_interval, self.release_day)
+    }
+
+    /// Rounds passed since start, not capped by `release_rounds`.
+    pub fn cur_round(&self, cur_ts: u64) -> u32 {
+        vault_schedule::round_at(self.start_timestamp, self.interval(), cur_ts)
+    }
+
+    pub fn unlocked(&self, cur_ts: u64) -> Balance {
+        vault_schedule::unlocked_at(
+            self.total_balance.0,
+            self.start_timestamp,
+            self.interval(),
+            self.release_rounds,
+            cur_ts,
+        )
+    }
+
+    pub fn end_timestamp(&self) -> TimestampSec {
+        self.round_timestamp(self.release_rounds)
+    }
+
+    /// When `cur_round` reaches the given round, i.e. that many intervals after start,
+    /// see `vault_schedule::round_timestamp`.
+    pub fn round_timestamp(&self, round: u32) -> TimestampSec {
+        vault_schedule::round_timestamp(self.start_timestamp, self.interval(), round)
+            .or_fail(VaultError::TimestampOverflow)
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Owner adds `amount` of tokens, already transferred to the vault, into the schedule.
+    /// The vault checks its token balance covers them before applying,
+    /// they are held as in flight meanwhile, so they can't be recovered or topped up twice.
+    pub fn top_up(&mut self, amount: WrappedBalance, extend_rounds: u32) -> Promise {
+        self.assert_owner();
+        require(amount.0 > 0, VaultError::ZeroAmount);
+        require(self.can_top_up(extend_rounds), VaultError::ScheduleEnded);
+        let expected = self.expected_token_balance();
+        self.in_flight_balance = self.in_flight_balance.add_or_fail(amount.0);
+
+        ext_fungible_token::ft_balance_of(
+            env::current_account_id(),
+            &self.token_account_id,
+            NO_DEPOSIT,
+            GAS_FOR_BALANCE_VIEW,
+        )
+        .then(ext_schedule::after_top_up_balance_of(
+            amount,
+            extend_rounds,
+            expected.into(),
+            &env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_AFTER_FT_TRANSFER,
+        ))
+    }
+}
+
+impl Contract {
+    /// Rounds passed over all tranches, counting on past the end with the last one.
+    pub(crate) fn cur_round(&self, cur_ts: u64) -> u32 {
+        let last = self.tranches.len().sub_or_fail(1);
+        // saturates like the round of a single tranche does
+        self.tranches
+            .iter()
+            .enumerate()
+            .map(|(index, tranche)| {
+                if index == last {
+                    tranche.cur_round(cur_ts)
+                } else {
+                    std::cmp::min(tranche.cur_round(cur_ts), tranche.release_rounds)
+                }
+            })
+            .fold(0, u32::saturating_add)
+    }
+
+    /// The first tranche not fully released.
+    fn cur_tranche_index(&self) -> Option<usize> {
+        let cur_ts = env::block_timestamp();
+        self.tranches
+            .iter()
+            .position(|tranche| tranche.cur_round(cur_ts) < tranche.release_rounds)
+    }
+
+    /// Spreading over remaining rounds needs some rounds left.
+    pub(crate) fn can_top_up(&self, extend_rounds: u32) -> bool {
+        extend_rounds > 0 || self.cur_tranche_index().is_some()
+    }
+
+    /// With `extend_rounds` being 0, `amount` is added to the rounds remaining
+    /// in the first tranche not fully released, which is split at current round.
+    /// Otherwise it's released over `extend_rounds` rounds in a tranche appended
+    /// after the schedule end, on the interval of the last tranche.
+    /// Either way, nothing of it is released at current round,
+    /// so released amount never decreases.
+    pub(crate) fn internal_top_up(&mut self, amount: Balance, extend_rounds: u32) {
+        let cur_ts = env::block_timestamp();
+        if extend_rounds == 0 {
+            let index = self.cur_tranche_index().or_fail(VaultError::ScheduleEnded);
+            let tranche = self.tranches[index].clone();
+            let cur_round = tranche.cur_round(cur_ts);
+            if cur_round == 0 {
+                self.tranches[index].total_balance.0 = tranche.total_balance.0.add_or_fail(amount);
+            } else {
+                let released = tranche.unlocked(cur_ts);
+                let head = Tranche {
+                    start_timestamp: tranche.start_timestamp,
+                    release_interval: tranche.release_interval,
+                    release_rounds: cur_round,
+                    total_balance: released.into(),
+                    release_day: tranche.release_day,
+                };
+                let tail = Tranche {
+                    start_timestamp: tranche.round_timestamp(cur_round),
+                    release_interval: tranche.release_interval,
+                    release_rounds: tranche.release_rounds.sub_or_fail(cur_round),
+                    total_balance: tranche.total_balance.0.sub_or_fail(released).add_or_fail(amount).into(),
+                    release_day: tranche.release_day,
+                };
+                self.tranches.splice(index..=index, vec![head, tail]);
+            }
+            log!("Top up {} into remaining rounds of tranche {}", amount, index);
+        } else {
+            let last = self.tranches.last().or_fail(VaultError::NoTranche);
+            let start_timestamp = std::cmp::max(
+                last.end_timestamp(),
+                last.round_timestamp(last.cur_round(cur_ts)),
+            );
+            let tranche = Tranche {
+                start_timestamp,
+                release_interval: last.release_interval,
+                release_rounds: extend_rounds,
+                total_balance: amount.into(),
+                release_day: last.release_day,
+            };
+            log!(
+                "Top up {} from {} over {} rounds",
+                amount,
+                start_timestamp,
+                extend_rounds
+            );
+            self.tranches.push(tranche);
+        }
+        self.total_balance = self.total_balance.add_or_fail(amount);
+    }
+
+    /// Tranches should be ordered without overlap, each releasing something.
+    pub(crate) fn assert_tranches(tranches: &[Tranche]) {
+        require(!tranches.is_empty(), VaultError::NoTranche);
+        let mut end_timestamp = 0;
+        for tranche in tranches {
+            require(
+                tranche.interval().is_valid() && tranche.release_rounds > 0,
+                VaultError::InvalidTranche,
+            );
+            require(tranche.start_timestamp >= end_timestamp, VaultError::TrancheOverlap);
+            end_timestamp = tranche.end_timestamp();
+        }
+    }
+}
+
+#[ext_contract(ext_schedule)]
+trait ScheduleCallbacks {
+    fn after_top_up_balance_of(&mut self, amount: WrappedBalance, extend_rounds: u32, expected: WrappedBalance) -> bool;
+}
+
+#[allow(dead_code)]
+trait ScheduleCallbacks {
+    fn after_top_up_balance_of(&mut self, amount: WrappedBalance, extend_rounds: u32, expected: WrappedBalance) -> bool;
+}
+
+#[near_bindgen]
+impl ScheduleCallbacks for Contract {
+    #[private]
+    fn after_top_up_balance_of(&mut self, amount: WrappedBalance, extend_rounds: u32, expected: WrappedBalance) -> bool {
+        let balance = match env::promise_result(0) {
+            PromiseResult::Successful(value) => serde_json::from_slice::<WrappedBalance>(&value)
+                .map(|balance| balance.0)
+                .unwrap_or(0),
+            _ => 0,
+        };
+        self.in_flight_balance = self.in_flight_balance.sub_or_fail(amount.0);
+        if balance < expected.0.add_or_fail(amount.0) {
+            log!("Top up {} not covered by balance {}", amount.0, balance);
+            return false;
+        }
+        if !self.can_top_up(extend_rounds) {
+            log!("Top up {} failed as schedule ended", amount.0);
+            return false;
+        }
+        self.internal_top_up(amount.0, extend_rounds);
+        true
+    }
+}
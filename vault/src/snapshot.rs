@@ -0,0 +1,167 @@
+//! Paged export of the full contract state for audits.
+use crate::*;
+use near_sdk::json_types::Base58CryptoHash;
+use near_sdk::serde::{Deserialize, Serialize};
+use std::convert::{TryFrom, TryInto};
+
+/// Layout version of the snapshot, bumped whenever its fields change after release.
+pub const SNAPSHOT_FORMAT: u32 = 1;
+
+/// Everything in `Contract` except accounts, ledgers sorted by account id.
+#[derive(BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+pub struct StateHeader {
+    pub format: u32,
+    pub version: String,
+    pub owner_id: AccountId,
+    pub token_account_id: AccountId,
+    pub total_balance: WrappedBalance,
+    pub tranches: Vec<Tranche>,
+    pub claimed_balance: WrappedBalance,
+    pub storage_pool: WrappedBalance,
+    pub registration_reserve: WrappedBalance,
+    pub keeper_reward: WrappedBalance,
+    pub delegations: Vec<(AccountId, WrappedBalance)>,
+    pub delegation_limit_bps: u32,
+    pub delegated_balance: WrappedBalance,
+    pub delegation_yield: WrappedBalance,
+    pub recalled: Vec<(AccountId, WrappedBalance)>,
+    pub account_transfers: Vec<(AccountId, AccountTransfer)>,
+    pub in_flight_balance: WrappedBalance,
+    pub claim_receivers: Vec<AccountId>,
+    pub total_accounts: u32,
+}
+
+/// Raw fields of an account, nothing calculated.
+#[derive(BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+pub struct AccountRecord {
+    pub account_id: AccountId,
+    pub start_timestamp: TimestampSec,
+    pub release_interval: TimestampSec,
+    pub release_rounds: u32,
+    pub last_claim_round: u32,
+    pub release_per_round: WrappedBalance,
+    pub release_day: Option<u8>,
+    pub storage_balance: WrappedBalance,
+    pub push_disabled: bool,
+    pub refunded_balance: WrappedBalance,
+    pub forfeited_balance: WrappedBalance,
+    pub end_timestamp: Option<TimestampSec>,
+    pub metadata: AccountMetadata,
+    pub clawback_reason: Option<String>,
+    pub pending_claims: u32,
+}
+
+impl From<&Account> for AccountRecord {
+    fn from(account: &Account) -> Self {
+        AccountRecord {
+            account_id: account.account_id.clone(),
+            start_timestamp: account.start_timestamp,
+            release_interval: account.release_interval,
+            release_rounds: account.release_rounds,
+            last_claim_round: account.last_claim_round,
+            release_per_round: account.release_per_round.into(),
+            release_day: account.release_day,
+            storage_balance: account.storage_balance.into(),
+            push_disabled: account.push_disabled,
+            refunded_balance: account.refunded_balance.into(),
+            forfeited_balance: account.forfeited_balance.into(),
+            end_timestamp: account.end_timestamp,
+            metadata: account.metadata.clone(),
+            clawback_reason: account.clawback_reason.clone(),
+            pending_claims: account.pending_claims,
+        }
+    }
+}
+
+/// A page of the state, accounts sorted by id starting from `from_index`.
+/// `state_hash` commits to the header and all accounts, the same on every page.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+pub struct StateSnapshot {
+    pub header: StateHeader,
+    pub from_index: u32,
+    pub accounts: Vec<AccountRecord>,
+    pub state_hash: Base58CryptoHash,
+}
+
+/// Bytes hashed into `state_hash`: borsh of the header then of all accounts sorted by id.
+pub fn state_bytes(header: &StateHeader, accounts: &[AccountRecord]) -> Vec<u8> {
+    let mut bytes = header.try_to_vec().unwrap();
+    bytes.extend(accounts.to_vec().try_to_vec().unwrap());
+    bytes
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Up to `limit` accounts sorted by id from `from_index`, with the rest of the state.
+    /// Hashing walks all accounts, so each page costs as much as the whole state.
+    pub fn export_state(&self, from_index: u32, limit: u32) -> StateSnapshot {
+        let header = self.state_header();
+        let mut accounts: Vec<AccountRecord> =
+            self.accounts.values().map(|account| account.into()).collect();
+        accounts.sort_by(|a, b| a.account_id.cmp(&b.account_id));
+        let state_hash: [u8; 32] = env::sha256(&state_bytes(&header, &accounts))
+            .as_slice()
+            .try_into()
+            .unwrap();
+        StateSnapshot {
+            header,
+            from_index,
+            accounts: accounts
+                .into_iter()
+                .skip(from_index as usize)
+                .take(limit as usize)
+                .collect(),
+            state_hash: state_hash.into(),
+        }
+    }
+}
+
+impl Contract {
+    fn state_header(&self) -> StateHeader {
+        let mut delegations: Vec<(AccountId, WrappedBalance)> = self
+            .delegations
+            .iter()
+            .map(|(contract_id, principal)| (contract_id.clone(), (*principal).into()))
+            .collect();
+        delegations.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut recalled: Vec<(AccountId, WrappedBalance)> = self
+            .recalled
+            .iter()
+            .map(|(contract_id, amount)| (contract_id.clone(), (*amount).into()))
+            .collect();
+        recalled.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut account_transfers: Vec<(AccountId, AccountTransfer)> = self
+            .account_transfers
+            .iter()
+            .map(|(account_id, transfer)| (account_id.clone(), transfer.clone()))
+            .collect();
+        account_transfers.sort_by(|a, b| a.0.cmp(&b.0));
+        StateHeader {
+            format: SNAPSHOT_FORMAT,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            owner_id: self.owner_id.clone(),
+            token_account_id: self.token_account_id.clone(),
+            total_balance: self.total_balance.into(),
+            tranches: self.tranches.clone(),
+            claimed_balance: self.claimed_balance.into(),
+            storage_pool: self.storage_pool.into(),
+            registration_reserve: self.registration_reserve.into(),
+            keeper_reward: self.keeper_reward.into(),
+            delegations,
+            delegation_limit_bps: self.delegation_limit_bps,
+            delegated_balance: self.delegated_balance.into(),
+            delegation_yield: self.delegation_yield.into(),
+            recalled,
+            account_transfers,
+            in_flight_balance: self.in_flight_balance.into(),
+            claim_receivers: self.get_claim_receivers(),
+            total_accounts: u32::try_from(self.accounts.len()).or_fail(VaultError::TooManyAccounts),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use crate::*;
+use near_contract_standards::storage_management::StorageBalance;
+use near_sdk::json_types::{ValidAccountId, U128};
+use near_sdk::{env, log, near_bindgen, AccountId, Balance, Promise, StorageUsage};
+
+#[near_bindgen]
+impl Contract {
+    /// Owner funds the pool that stakes storage for account records.
+    /// Returns the pool balance after deposit.
+    #[payable]
+    pub fn storage_pool_deposit(&mut self) -> U128 {
+        self.assert_owner();
+        self.storage_pool = self.storage_pool.add_or_fail(env::attached_deposit());
+        self.storage_pool.into()
+    }
+
+    /// Owner withdraws unused NEAR from the storage pool.
+    pub fn storage_pool_withdraw(&mut self, amount: U128) -> Promise {
+        self.assert_owner();
+        let amount: Balance = amount.into();
+        require(amount <= self.storage_pool, VaultError::InsufficientStorage);
+        self.storage_pool = self.storage_pool.sub_or_fail(amount);
+        log!("Withdraw {} from storage pool to {}", amount, self.owner_id);
+        Promise::new(self.owner_id.clone()).transfer(amount)
+    }
+
+    /// Owner funds the reserve that pays token storage fee
+    /// when registering beneficiaries to token contract on claim.
+    #[payable]
+    pub fn registration_reserve_deposit(&mut self) -> U128 {
+        self.assert_owner();
+        self.registration_reserve = self.registration_reserve.add_or_fail(env::attached_deposit());
+        self.registration_reserve.into()
+    }
+
+    /// Owner withdraws unused NEAR from the registration reserve.
+    pub fn registration_reserve_withdraw(&mut self, amount: U128) -> Promise {
+        self.assert_owner();
+        let amount: Balance = amount.into();
+        require(amount <= self.registration_reserve, VaultError::InsufficientReserve);
+        self.registration_reserve = self.registration_reserve.sub_or_fail(amount);
+        log!("Withdraw {} from registration reserve to {}", amount, self.owner_id);
+        Promise::new(self.owner_id.clone()).transfer(amount)
+    }
+
+    /// NEP-145 style view of NEAR staked for the record of the given account,
+    /// the whole stake is locked until the account is removed.
+    pub fn storage_balance_of(&self, account_id: ValidAccountId) -> Option<StorageBalance> {
+        self.accounts.get::<String>(&account_id.into())
+        .map(|account| StorageBalance {
+            total: account.storage_balance.into(),
+            available: 0.into(),
+        })
+    }
+}
+
+impl Contract {
+    /// Moves the storage cost of `bytes` out of the pool,
+    /// attached deposit should have been put into the pool before calling this.
+    pub(crate) fn internal_stake_storage(&mut self, bytes: StorageUsage) -> Balance {
+        let cost = Balance::from(bytes).mul_or_fail(env::storage_byte_cost());
+        require(cost <= self.storage_pool, VaultError::InsufficientStorage);
+        self.storage_pool = self.storage_pool.sub_or_fail(cost);
+        cost
+    }
+
+    /// Stakes storage for growth of the account record since it took `prev_usage` bytes,
+    /// or returns the stake of bytes it shrank by to the pool.
+    pub(crate) fn internal_restake_storage(&mut self, account_id: &AccountId, prev_usage: StorageUsage) {
+        let usage = self.accounts.get(account_id).or_fail(VaultError::AccountNotExist).storage_usage();
+        let staked = if usage > prev_usage {
+            self.internal_stake_storage(usage.sub_or_fail(prev_usage))
+        } else {
+            0
+        };
+        let account = self.accounts.get_mut(account_id).or_fail(VaultError::AccountNotExist);
+        account.storage_balance = account.storage_balance.add_or_fail(staked);
+        if usage < prev_usage {
+            let released = std::cmp::min(
+                Balance::from(prev_usage.sub_or_fail(usage)).mul_or_fail(env::storage_byte_cost()),
+                account.storage_balance,
+            );
+            account.storage_balance = account.storage_balance.sub_or_fail(released);
+            self.storage_pool = self.storage_pool.add_or_fail(released);
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! Helpers of unit tests, which run the contract methods directly on a mocked blockchain.
+use std::convert::TryInto;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use near_sdk::json_types::{ValidAccountId, U128};
+use near_sdk::test_utils::VMContextBuilder;
+use near_sdk::{testing_env, Balance, MockedBlockchain, PromiseResult};
+
+use crate::errors::VaultError;
+use crate::utils::{to_nano, TimestampSec};
+use crate::Contract;
+
+pub const NEAR: Balance = 1_000_000_000_000_000_000_000_000;
+
+pub fn id(account_id: &str) -> ValidAccountId {
+    account_id.try_into().unwrap()
+}
+
+/// Calls from `predecessor` at `timestamp` in sec, with `deposit` attached.
+pub fn set_context(predecessor: &str, timestamp: TimestampSec, deposit: Balance) {
+    set_context_with_results(predecessor, timestamp, deposit, vec![]);
+}
+
+/// Same as `set_context`, for callbacks seeing `promise_results`.
+pub fn set_context_with_results(
+    predecessor: &str,
+    timestamp: TimestampSec,
+    deposit: Balance,
+    promise_results: Vec<PromiseResult>,
+) {
+    testing_env!(
+        VMContextBuilder::new()
+            .current_account_id(id("vault"))
+            .predecessor_account_id(id(predecessor))
+            .block_timestamp(to_nano(timestamp))
+            .attached_deposit(deposit)
+            .build(),
+        Default::default(),
+        Default::default(),
+        Default::default(),
+        promise_results
+    );
+}
+
+/// 1000 released over 10 rounds of 10 sec from 100,
+/// alice gets 50 per round on the same schedule.
+pub fn setup() -> Contract {
+    set_context("owner", 0, NEAR);
+    let mut contract = Contract::new(id("owner"), id("token"), U128(1000), 100, 10, 10);
+    contract.add_account(id("alice"), 100, 10, 10, U128(50), None);
+    contract
+}
+
+/// The error the call fails with, checking its message is the code,
+/// optionally followed by details.
+pub fn failure<R>(call: impl FnOnce() -> R) -> VaultError {
+    let payload = match catch_unwind(AssertUnwindSafe(call)) {
+        Ok(_) => panic!("expect the call to fail"),
+        Err(payload) => payload,
+    };
+    let message = payload
+        .downcast_ref::<String>()
+        .cloned()
+        .or_else(|| payload.downcast_ref::<&str>().map(|message| message.to_string()))
+        .unwrap();
+    // mocked blockchain reports the message as a host error
+    let message = message
+        .split("panic_msg: \"")
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .unwrap_or(&message);
+    let error = VaultError::from_message(message).unwrap_or_else(|| panic!("unknown failure {}", message));
+    assert!(message == error.code() || message.starts_with(&format!("{}: ", error.code())));
+    error
+}
@@ -0,0 +1,40 @@
+use crate::*;
+use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+use near_sdk::json_types::{ValidAccountId, U128};
+use near_sdk::serde::Deserialize;
+use near_sdk::serde_json;
+use near_sdk::{env, log, near_bindgen, AccountId, PromiseOrValue};
+
+/// Message of `ft_on_transfer`, e.g. `{"TopUp": {"extend_rounds": 0}}`.
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+enum TokenReceiverMessage {
+    TopUp { extend_rounds: u32 },
+}
+
+#[near_bindgen]
+impl FungibleTokenReceiver for Contract {
+    /// Accepts top-ups from owner and tokens returned by delegation targets,
+    /// transfers from anyone else are refunded.
+    fn ft_on_transfer(
+        &mut self,
+        sender_id: ValidAccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        require(env::predecessor_account_id() == self.token_account_id, VaultError::IllegalToken);
+        let sender_id: AccountId = sender_id.into();
+        if let Ok(TokenReceiverMessage::TopUp { extend_rounds }) = serde_json::from_str(&msg) {
+            require(sender_id == self.owner_id, VaultError::NotAllowed);
+            require(self.can_top_up(extend_rounds), VaultError::ScheduleEnded);
+            self.internal_top_up(amount.into(), extend_rounds);
+            PromiseOrValue::Value(U128(0))
+        } else if self.delegations.contains_key(&sender_id) {
+            self.internal_return_delegation(&sender_id, amount.into());
+            PromiseOrValue::Value(U128(0))
+        } else {
+            log!("Refund {} from {}, msg is {}", amount.0, sender_id, msg);
+            PromiseOrValue::Value(amount)
+        }
+    }
+}
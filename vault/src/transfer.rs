@@ -0,0 +1,76 @@
+use crate::*;
+use crate::events::emit_account_transferred;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, log, near_bindgen, AccountId};
+
+/// Beneficiary asks to move its account to a new wallet,
+/// the new wallet accepts after owner approves.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+pub struct AccountTransfer {
+    pub new_account_id: AccountId,
+    pub approved: bool,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Beneficiary requests to rekey its account to `new_account_id`,
+    /// replacing any previous request.
+    pub fn request_account_transfer(&mut self, new_account_id: ValidAccountId) {
+        let account_id = env::predecessor_account_id();
+        let new_account_id: AccountId = new_account_id.into();
+        require(self.accounts.contains_key(&account_id), VaultError::AccountNotExist);
+        require(!self.accounts.contains_key(&new_account_id), VaultError::AccountExists);
+        log!("Account transfer requested from {} to {}", account_id, new_account_id);
+        self.account_transfers.insert(
+            account_id,
+            AccountTransfer {
+                new_account_id,
+                approved: false,
+            },
+        );
+    }
+
+    pub fn approve_account_transfer(&mut self, account_id: ValidAccountId) {
+        self.assert_owner();
+        let account_id: AccountId = account_id.into();
+        let transfer = self.account_transfers.get_mut(&account_id).or_fail(VaultError::NoAccountTransfer);
+        transfer.approved = true;
+        log!("Account transfer approved from {} to {}", account_id, transfer.new_account_id);
+    }
+
+    /// Either the beneficiary or owner can cancel a pending transfer.
+    pub fn cancel_account_transfer(&mut self, account_id: ValidAccountId) {
+        let account_id: AccountId = account_id.into();
+        if env::predecessor_account_id() != account_id {
+            self.assert_owner();
+        }
+        self.account_transfers.remove(&account_id).or_fail(VaultError::NoAccountTransfer);
+        log!("Account transfer cancelled for {}", account_id);
+    }
+
+    /// The new wallet accepts an approved transfer, the whole account record,
+    /// including claim progress, moves to the new key.
+    /// Storage difference caused by the new key is settled with storage pool.
+    /// Fails while a claim transfer to the old wallet is in flight,
+    /// since its rollback is resolved against the old key.
+    pub fn accept_account_transfer(&mut self, account_id: ValidAccountId) {
+        let account_id: AccountId = account_id.into();
+        let new_account_id = env::predecessor_account_id();
+        let transfer = self.account_transfers.get(&account_id).or_fail(VaultError::NoAccountTransfer);
+        require(transfer.new_account_id == new_account_id, VaultError::NotAllowed);
+        require(transfer.approved, VaultError::NotApproved);
+        require(!self.accounts.contains_key(&new_account_id), VaultError::AccountExists);
+        let account = self.accounts.get(&account_id).or_fail(VaultError::AccountNotExist);
+        require(account.pending_claims == 0, VaultError::ClaimInFlight);
+        self.account_transfers.remove(&account_id);
+
+        let mut account = self.accounts.remove(&account_id).or_fail(VaultError::AccountNotExist);
+        self.storage_pool = self.storage_pool.add_or_fail(account.storage_balance);
+        account.account_id = new_account_id.clone();
+        account.storage_balance = self.internal_stake_storage(account.storage_usage());
+        emit_account_transferred(&account_id, &new_account_id, account.last_claim_round);
+        self.accounts.insert(new_account_id, account);
+    }
+}
@@ -0,0 +1,229 @@
+//! Two-phase upgrade: owner stages code, then deploys it after a delay.
+use crate::*;
+use near_sdk::json_types::Base58CryptoHash;
+use near_sdk::serde_json::{self, json};
+use near_sdk::{env, is_promise_success, log, near_bindgen, CryptoHash, Timestamp};
+
+const STAGED_CODE_KEY: &[u8] = b"STAGED_CODE";
+const UPGRADE_KEY: &[u8] = b"UPGRADE";
+
+/// Kept apart from `Contract` so it isn't touched by state migrations.
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct UpgradeState {
+    // hash of the code under STAGED_CODE_KEY and when it was staged
+    pub staged_hash: Option<CryptoHash>,
+    pub staged_at: Timestamp,
+    // code put in place by the last deploy_staged_code
+    pub deployed_hash: Option<CryptoHash>,
+    // code replaced by the last deploy_staged_code, to stage again if the new one turns out bad
+    pub rollback_hash: Option<CryptoHash>,
+}
+
+impl UpgradeState {
+    fn read() -> Self {
+        env::storage_read(UPGRADE_KEY)
+            .map(|data| UpgradeState::try_from_slice(&data).or_fail(VaultError::UnknownUpgradeState))
+            .unwrap_or_default()
+    }
+
+    fn write(&self) {
+        env::storage_write(UPGRADE_KEY, &self.try_to_vec().unwrap());
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Deploys the staged code and calls `migrate`, once `UPGRADE_DELAY` passed since staging,
+    /// rollbacks included. The staged code is dropped and the hashes are moved on before deploying.
+    /// Returns the result of `after_deploy_staged_code`.
+    pub fn deploy_staged_code(&mut self, expected_hash: Base58CryptoHash) {
+        self.assert_owner();
+        let mut state = UpgradeState::read();
+        let code_hash = state.staged_hash.or_fail(VaultError::NoStagedCode);
+        require(code_hash == CryptoHash::from(expected_hash), VaultError::CodeHashMismatch);
+        require(
+            env::block_timestamp() >= state.staged_at.add_or_fail(to_nano(UPGRADE_DELAY)),
+            VaultError::UpgradeDelay,
+        );
+        let code = env::storage_read(STAGED_CODE_KEY).or_fail(VaultError::NoStagedCode);
+        env::storage_remove(STAGED_CODE_KEY);
+        let replaced_rollback_hash: Option<Base58CryptoHash> = state.rollback_hash.map(|hash| hash.into());
+        state.staged_hash = None;
+        state.rollback_hash = state.deployed_hash;
+        state.deployed_hash = Some(code_hash);
+        state.write();
+        log!("Deploy staged code {}", String::from(&expected_hash));
+
+        // actions are added right away, so the deploy is paid for when measuring gas left
+        let promise_id = env::promise_batch_create(env::current_account_id());
+        env::promise_batch_action_deploy_contract(promise_id, &code);
+        let migrate_gas = env::prepaid_gas()
+            .sub_or_fail(env::used_gas())
+            .sub_or_fail(GAS_FOR_MIGRATE_CALL)
+            .sub_or_fail(GAS_FOR_AFTER_DEPLOY);
+        env::promise_batch_action_function_call(promise_id, b"migrate", &[], NO_DEPOSIT, migrate_gas);
+        let args = json!({ "code_hash": expected_hash, "rollback_hash": replaced_rollback_hash });
+        let callback_id = env::promise_then(
+            promise_id,
+            env::current_account_id(),
+            b"after_deploy_staged_code",
+            &serde_json::to_vec(&args).unwrap(),
+            NO_DEPOSIT,
+            GAS_FOR_AFTER_DEPLOY,
+        );
+        env::promise_return(callback_id);
+    }
+
+    /// Drops the staged code and frees its storage.
+    pub fn cancel_staged_code(&mut self) {
+        self.assert_owner();
+        let mut state = UpgradeState::read();
+        require(state.staged_hash.is_some(), VaultError::NoStagedCode);
+        state.staged_hash = None;
+        state.write();
+        env::storage_remove(STAGED_CODE_KEY);
+        log!("Staged code cancelled");
+    }
+
+    pub fn get_staged_code_hash(&self) -> Option<Base58CryptoHash> {
+        UpgradeState::read().staged_hash.map(|hash| hash.into())
+    }
+
+    /// Hash of the code replaced by the last deploy, if known.
+    pub fn get_rollback_code_hash(&self) -> Option<Base58CryptoHash> {
+        UpgradeState::read().rollback_hash.map(|hash| hash.into())
+    }
+
+    /// Owner records the hash of the running code, which the vault can't read itself,
+    /// so the first deploy keeps it for rollback. Only until a deploy recorded one.
+    pub fn set_deployed_code_hash(&mut self, code_hash: Base58CryptoHash) {
+        self.assert_owner();
+        let mut state = UpgradeState::read();
+        require(state.deployed_hash.is_none(), VaultError::CodeHashKnown);
+        state.deployed_hash = Some(code_hash.into());
+        state.write();
+        log!("Deployed code {}", String::from(&code_hash));
+    }
+
+    /// Hash of the running code, once recorded.
+    pub fn get_deployed_code_hash(&self) -> Option<Base58CryptoHash> {
+        UpgradeState::read().deployed_hash.map(|hash| hash.into())
+    }
+}
+
+#[allow(dead_code)]
+trait UpgradeCallbacks {
+    fn after_deploy_staged_code(&mut self, code_hash: Base58CryptoHash, rollback_hash: Option<Base58CryptoHash>) -> bool;
+}
+
+#[near_bindgen]
+impl UpgradeCallbacks for Contract {
+    /// Deploy and migrate roll back together on failure, the running code keeps its hashes then,
+    /// `rollback_hash` being the one before the deploy. The staged code is gone either way.
+    #[private]
+    fn after_deploy_staged_code(&mut self, code_hash: Base58CryptoHash, rollback_hash: Option<Base58CryptoHash>) -> bool {
+        let promise_success = is_promise_success();
+        if !promise_success {
+            let mut state = UpgradeState::read();
+            if state.deployed_hash == Some(code_hash.into()) {
+                state.deployed_hash = state.rollback_hash;
+                state.rollback_hash = rollback_hash.map(|hash| hash.into());
+                state.write();
+            }
+            log!("Deploy of staged code {} failed", String::from(&code_hash));
+        }
+        promise_success
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod stage {
+    use super::*;
+    use std::convert::TryInto;
+
+    /// Owner stores code to deploy later, replacing any staged before.
+    /// Takes as input non serialized set of bytes of the code,
+    /// its storage is paid from the vault balance.
+    #[no_mangle]
+    pub extern "C" fn stage_code() {
+        env::setup_panic_hook();
+        env::set_blockchain_interface(Box::new(near_blockchain::NearBlockchain {}));
+        let contract: Contract = env::state_read().or_fail(VaultError::NotInitialized);
+        contract.assert_owner();
+        let code = env::input().or_fail(VaultError::NoCode);
+        require(!code.is_empty(), VaultError::NoCode);
+        let code_hash: CryptoHash = env::sha256(&code).as_slice().try_into().unwrap();
+        env::storage_write(STAGED_CODE_KEY, &code);
+        let mut state = UpgradeState::read();
+        state.staged_hash = Some(code_hash);
+        state.staged_at = env::block_timestamp();
+        state.write();
+        log!("Staged code {}", String::from(&Base58CryptoHash::from(code_hash)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::{testing_env, MockedBlockchain, PromiseResult};
+
+    use super::*;
+    use crate::test_utils::*;
+
+    /// Owner calls at `timestamp` with gas the deploy can be sized from.
+    fn set_owner_context(timestamp: TimestampSec) {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id(id("vault"))
+            .predecessor_account_id(id("owner"))
+            .block_timestamp(to_nano(timestamp))
+            .prepaid_gas(300_000_000_000_000)
+            .build());
+    }
+
+    fn stage(code_hash: CryptoHash, staged_at: TimestampSec) {
+        env::storage_write(STAGED_CODE_KEY, &[0]);
+        UpgradeState { staged_hash: Some(code_hash), staged_at: to_nano(staged_at), ..UpgradeState::read() }.write();
+    }
+
+    #[test]
+    fn rollback_from_first_deploy() {
+        let mut contract = setup();
+        set_context("owner", 150, 0);
+        contract.set_deployed_code_hash([1; 32].into());
+        assert_eq!(failure(|| contract.set_deployed_code_hash([2; 32].into())), VaultError::CodeHashKnown);
+        stage([2; 32], 150);
+
+        println!("*** Hashes move on before deploying");
+        set_owner_context(150 + UPGRADE_DELAY);
+        contract.deploy_staged_code([2; 32].into());
+        assert_eq!(contract.get_rollback_code_hash(), Some([1; 32].into()));
+        assert_eq!(contract.get_deployed_code_hash(), Some([2; 32].into()));
+        assert_eq!(contract.get_staged_code_hash(), None);
+        assert!(env::storage_read(STAGED_CODE_KEY).is_none());
+
+        println!("*** Rollback waits for the delay too");
+        stage([1; 32], 200 + UPGRADE_DELAY);
+        set_owner_context(200 + 2 * UPGRADE_DELAY - 1);
+        assert_eq!(failure(|| contract.deploy_staged_code([1; 32].into())), VaultError::UpgradeDelay);
+        set_owner_context(200 + 2 * UPGRADE_DELAY);
+        contract.deploy_staged_code([1; 32].into());
+        assert_eq!(contract.get_rollback_code_hash(), Some([2; 32].into()));
+        assert_eq!(contract.get_deployed_code_hash(), Some([1; 32].into()));
+    }
+
+    #[test]
+    fn failed_deploy_keeps_hashes() {
+        let mut contract = setup();
+        set_context("owner", 150, 0);
+        contract.set_deployed_code_hash([1; 32].into());
+        stage([2; 32], 150);
+        set_owner_context(150 + UPGRADE_DELAY);
+        contract.deploy_staged_code([2; 32].into());
+
+        set_context_with_results("vault", 150 + UPGRADE_DELAY, 0, vec![PromiseResult::Failed]);
+        assert!(!contract.after_deploy_staged_code([2; 32].into(), None));
+        assert_eq!(contract.get_rollback_code_hash(), None);
+        assert_eq!(contract.get_deployed_code_hash(), Some([1; 32].into()));
+        assert_eq!(contract.get_staged_code_hash(), None);
+    }
+}
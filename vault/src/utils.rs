@@ -1,16 +1,65 @@
 
 
 use near_sdk::{
- Timestamp, Gas, Balance
+ env, Timestamp, Gas, Balance
 };
-pub type TimestampSec = u32;
+pub use vault_schedule::{Interval, TimestampSec};
+
+use crate::errors::{OrFail, VaultError};
 
 pub const GAS_FOR_FT_TRANSFER: Gas = 10_000_000_000_000;
 pub const GAS_FOR_AFTER_FT_TRANSFER: Gas = 10_000_000_000_000;
+/// Gas for token contract, receiver and resolve of a transfer call.
+pub const GAS_FOR_FT_TRANSFER_CALL: Gas = 80_000_000_000_000;
+pub const GAS_FOR_STORAGE_VIEW: Gas = 5_000_000_000_000;
+pub const GAS_FOR_STORAGE_DEPOSIT: Gas = 10_000_000_000_000;
+pub const GAS_FOR_AFTER_STORAGE_DEPOSIT: Gas = 5_000_000_000_000;
+pub const GAS_FOR_AFTER_STORAGE_BALANCE_OF: Gas = 15_000_000_000_000
+    + GAS_FOR_STORAGE_DEPOSIT + GAS_FOR_AFTER_STORAGE_DEPOSIT
+    + GAS_FOR_FT_TRANSFER + GAS_FOR_AFTER_FT_TRANSFER;
+
+/// Gas for iterating and scheduling promises of one account in `distribute`.
+pub const GAS_FOR_DISTRIBUTE_ACCOUNT: Gas = 5_000_000_000_000;
+pub const GAS_FOR_KEEPER_REWARD: Gas = 5_000_000_000_000
+    + GAS_FOR_FT_TRANSFER + GAS_FOR_AFTER_FT_TRANSFER;
+
+/// Gas for delegation target to unstake and transfer call tokens back to vault.
+pub const GAS_FOR_RECALL: Gas = 20_000_000_000_000 + GAS_FOR_FT_TRANSFER_CALL;
+
+pub const GAS_FOR_AFTER_RECALL: Gas = 5_000_000_000_000;
+
+pub const GAS_FOR_BALANCE_VIEW: Gas = 5_000_000_000_000;
+pub const GAS_FOR_AFTER_BALANCE_OF: Gas = 10_000_000_000_000
+    + GAS_FOR_FT_TRANSFER + GAS_FOR_AFTER_FT_TRANSFER;
+
+/// Kept for the rest of `deploy_staged_code` and the fees of `migrate` and its callback,
+/// `migrate` gets the remaining gas.
+pub const GAS_FOR_MIGRATE_CALL: Gas = 20_000_000_000_000;
+pub const GAS_FOR_AFTER_DEPLOY: Gas = 10_000_000_000_000;
+
+/// Staged code can be deployed only after this many seconds.
+pub const UPGRADE_DELAY: TimestampSec = 24 * 3600;
+
+pub const MAX_BPS: u32 = 10_000;
+
+/// Bound of points in `get_projection` to stay within view gas.
+pub const MAX_PROJECTION_POINTS: u32 = 500;
+
+/// Bound of bytes in each text field of account metadata, and in a clawback reason.
+pub const MAX_METADATA_LEN: usize = 64;
+
+pub const STATE_KEY: &[u8] = b"STATE";
+/// Layout version of the state, absent before 0.4.0.
+pub const STATE_VERSION_KEY: &[u8] = b"STATE_VERSION";
 
 pub const ONE_YOCTO: Balance = 1;
 pub const NO_DEPOSIT: Balance = 0;
 
 pub(crate) fn to_nano(timestamp: TimestampSec) -> Timestamp {
-    Timestamp::from(timestamp) * 10u64.pow(9)
-}
\ No newline at end of file
+    vault_schedule::to_nano(timestamp).or_fail(VaultError::TimestampOverflow)
+}
+
+/// Block timestamp in whole sec.
+pub(crate) fn cur_sec() -> TimestampSec {
+    env::block_timestamp() / to_nano(1)
+}
@@ -3,6 +3,7 @@ use near_sdk::{
     near_bindgen, AccountId,
 };
 use near_sdk::serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
@@ -19,14 +20,14 @@ pub struct Stats {
     // the static total balance in this vault
     pub total_balance: WrappedBalance,
 
-    // the start point of linear release 
+    // the start point of linear release, of the first tranche
     pub start_timestamp: TimestampSec,
 
-    // the duration of each release round
+    // the duration of each release round, of the first tranche
     pub release_interval: TimestampSec,
 
-    // the total release rounds, 
-    // we can infer release_per_round = total_balance / release_rounds
+    // the total release rounds of all tranches,
+    // for single tranche we can infer release_per_round = total_balance / release_rounds
     pub release_rounds: u32,
 
     // already claimed balance, includes account claims and payments
@@ -36,7 +37,60 @@ pub struct Stats {
     pub locked_balance: WrappedBalance,  // still locked in this vault
     pub liquid_balance: WrappedBalance,  // liquid balance in this vault
     pub unclaimed_balance: WrappedBalance,  // can be claimed for current
-    pub current_round: u32,  // the current release round, start from 1
+    pub current_round: u32,  // the current release round over all tranches, start from 1
+
+    // NEAR left in pool to stake storage for new accounts
+    pub storage_pool_balance: WrappedBalance,
+    // NEAR left in reserve to register beneficiaries to token contract
+    pub registration_reserve: WrappedBalance,
+    // token reward to keeper for each account distributed
+    pub keeper_reward: WrappedBalance,
+
+    // principal of locked balance delegated to whitelisted contracts
+    pub delegated_balance: WrappedBalance,
+    // the max delegated principal, in bps of locked balance
+    pub delegation_limit_bps: u32,
+    // returned by delegations beyond principal, not part of release schedule
+    pub delegation_yield: WrappedBalance,
+
+    // managed tokens held for promises not resolved yet
+    pub in_flight_balance: WrappedBalance,
+
+    // breakdown of the global schedule
+    pub tranches: Vec<TrancheOutput>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+pub struct TrancheOutput {
+    pub start_timestamp: TimestampSec,
+    pub release_interval: TimestampSec,
+    pub release_rounds: u32,
+    pub total_balance: WrappedBalance,
+    // if set, rounds end on this day of each month instead of release_interval
+    pub release_day: Option<u8>,
+    // following are calculated from current env
+    pub current_round: u32,  // capped by release_rounds
+    pub unlocked_balance: WrappedBalance,
+    pub locked_balance: WrappedBalance,
+}
+
+impl From<&Tranche> for TrancheOutput {
+    fn from(tranche: &Tranche) -> Self {
+        let cur_ts = env::block_timestamp();
+        let unlocked = tranche.unlocked(cur_ts);
+        TrancheOutput {
+            start_timestamp: tranche.start_timestamp,
+            release_interval: tranche.release_interval,
+            release_rounds: tranche.release_rounds,
+            total_balance: tranche.total_balance,
+            release_day: tranche.release_day,
+            current_round: std::cmp::min(tranche.cur_round(cur_ts), tranche.release_rounds),
+            unlocked_balance: unlocked.into(),
+            locked_balance: tranche.total_balance.0.sub_or_fail(unlocked).into(),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -54,8 +108,72 @@ pub struct AccountOutput {
     pub last_claim_round: u32,
     // total_release = release_rounds * release_per_round
     pub release_per_round: WrappedBalance,
+    // if set, rounds end on this day of each month instead of release_interval
+    pub release_day: Option<u8>,
     // unclaimed amount
     pub unclaimed_amount: WrappedBalance,
+    // if true, keepers won't push claimable balance to this account
+    pub push_disabled: bool,
+    // returned by receivers of claim_and_call, included in unclaimed amount
+    pub refunded_balance: WrappedBalance,
+    // cut from the schedule by clawback or termination, not in release_rounds any more
+    pub forfeited_balance: WrappedBalance,
+    // termination date, rounds completing after it were forfeited
+    pub end_timestamp: Option<TimestampSec>,
+    pub metadata: AccountMetadata,
+    // reason of the last clawback
+    pub clawback_reason: Option<String>,
+}
+
+impl From<&Account> for AccountOutput {
+    fn from(account: &Account) -> Self {
+        AccountOutput {
+            account_id: account.account_id.clone(),
+            start_timestamp: account.start_timestamp,
+            release_interval: account.release_interval,
+            release_rounds: account.release_rounds,
+            last_claim_round: account.last_claim_round,
+            release_per_round: account.release_per_round.into(),
+            release_day: account.release_day,
+            unclaimed_amount: account.unclaimed_amount(env::block_timestamp()).into(),
+            push_disabled: account.push_disabled,
+            refunded_balance: account.refunded_balance.into(),
+            forfeited_balance: account.forfeited_balance.into(),
+            end_timestamp: account.end_timestamp,
+            metadata: account.metadata.clone(),
+            clawback_reason: account.clawback_reason.clone(),
+        }
+    }
+}
+
+/// Totals of accounts sharing a cost center.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+pub struct CostCenterOutput {
+    // None for accounts without one
+    pub cost_center: Option<String>,
+    pub accounts: u32,
+    // release_per_round * release_rounds of all accounts
+    pub total_balance: WrappedBalance,
+    // sent out of the vault, refunded balance excluded
+    pub claimed_balance: WrappedBalance,
+    pub unclaimed_balance: WrappedBalance,
+    pub forfeited_balance: WrappedBalance,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+pub struct ProjectionPoint {
+    pub timestamp: TimestampSec,
+    pub locked_balance: WrappedBalance,
+    // assuming no claims or payments from now on
+    pub liquid_balance: WrappedBalance,
+    // unclaimed amount of all accounts by then
+    pub committed_balance: WrappedBalance,
+    // liquid balance can't cover what accounts could claim
+    pub shortfall: bool,
 }
 
 #[near_bindgen]
@@ -69,38 +187,245 @@ impl Contract {
             token_account_id: self.token_account_id.clone(),
             total_balance: self.total_balance.into(),
             claimed_balance: self.claimed_balance.into(),
-            start_timestamp: self.start_timestamp,
-            release_interval: self.release_interval,
-            release_rounds: self.release_rounds,
-            locked_balance: (self.total_balance - unlocked).into(),
+            start_timestamp: self.tranches[0].start_timestamp,
+            release_interval: self.tranches[0].release_interval,
+            release_rounds: self
+                .tranches
+                .iter()
+                .fold(0, |rounds: u32, tranche| rounds.saturating_add(tranche.release_rounds)),
+            locked_balance: self.total_balance.sub_or_fail(unlocked).into(),
             liquid_balance: liquid_balance.into(),
             unclaimed_balance: unclaimed_balance.into(),
             current_round: cur_round,
+            storage_pool_balance: self.storage_pool.into(),
+            registration_reserve: self.registration_reserve.into(),
+            keeper_reward: self.keeper_reward.into(),
+            delegated_balance: self.delegated_balance.into(),
+            delegation_limit_bps: self.delegation_limit_bps,
+            delegation_yield: self.delegation_yield.into(),
+            in_flight_balance: self.in_flight_balance.into(),
+            tranches: self.tranches.iter().map(|tranche| tranche.into()).collect(),
         }
     }
 
     pub fn get_account(&self, account_id: ValidAccountId) -> Option<AccountOutput> {
         self.accounts.get::<String>(&account_id.into())
-        .map(|account| AccountOutput {
-            account_id: account.account_id.clone(),
-            start_timestamp: account.start_timestamp,
-            release_interval: account.release_interval,
-            release_rounds: account.release_rounds,
-            last_claim_round: account.last_claim_round,
-            release_per_round: account.release_per_round.into(),
-            unclaimed_amount: account.unclaimed_amount(env::block_timestamp()).into(),
-        })
+        .map(|account| account.into())
     }
 
     pub fn list_accounts(&self) -> Vec<AccountOutput> {
-        self.accounts.values().map(|account| AccountOutput {
-            account_id: account.account_id.clone(),
-            start_timestamp: account.start_timestamp,
-            release_interval: account.release_interval,
-            release_rounds: account.release_rounds,
-            last_claim_round: account.last_claim_round,
-            release_per_round: account.release_per_round.into(),
-            unclaimed_amount: account.unclaimed_amount(env::block_timestamp()).into(),
-        }).collect()
+        self.accounts.values().map(|account| account.into()).collect()
+    }
+
+    /// Balances at each `step` seconds from `from_ts` to `to_ts` inclusive,
+    /// as if nothing is claimed or paid in between.
+    /// Past points saturate at 0 where claims already made exceed what was released by then.
+    pub fn get_projection(&self, from_ts: TimestampSec, to_ts: TimestampSec, step: TimestampSec) -> Vec<ProjectionPoint> {
+        require(step > 0 && from_ts <= to_ts, VaultError::InvalidRange);
+        let points = to_ts.sub_or_fail(from_ts) / step;
+        require(points < u64::from(MAX_PROJECTION_POINTS), VaultError::TooManyPoints);
+        (0..=points)
+            .map(|index| {
+                let timestamp = from_ts.add_or_fail(index.mul_or_fail(step));
+                let cur_ts = to_nano(timestamp);
+                let (_, unlocked) = self.round_and_total_unlock_at(cur_ts);
+                let (liquid_balance, committed_balance) = self.projected_funding_balance_at(cur_ts);
+                ProjectionPoint {
+                    timestamp,
+                    locked_balance: self.total_balance.sub_or_fail(unlocked).into(),
+                    liquid_balance: liquid_balance.into(),
+                    committed_balance: committed_balance.into(),
+                    shortfall: liquid_balance < committed_balance,
+                }
+            })
+            .collect()
+    }
+
+    /// Totals of current accounts by cost center of their metadata, sorted by cost center.
+    pub fn get_cost_center_totals(&self) -> Vec<CostCenterOutput> {
+        let cur_ts = env::block_timestamp();
+        let mut totals: BTreeMap<Option<String>, CostCenterOutput> = BTreeMap::new();
+        for account in self.accounts.values() {
+            let cost_center = account.metadata.cost_center.clone();
+            let total = totals.entry(cost_center.clone()).or_insert_with(|| CostCenterOutput {
+                cost_center,
+                accounts: 0,
+                total_balance: 0.into(),
+                claimed_balance: 0.into(),
+                unclaimed_balance: 0.into(),
+                forfeited_balance: 0.into(),
+            });
+            let claimed = account
+                .release_per_round
+                .mul_or_fail(u128::from(account.last_claim_round))
+                .saturating_sub(account.refunded_balance);
+            total.accounts = total.accounts.add_or_fail(1);
+            total.total_balance.0 = total
+                .total_balance
+                .0
+                .add_or_fail(account.release_per_round.mul_or_fail(u128::from(account.release_rounds)));
+            total.claimed_balance.0 = total.claimed_balance.0.add_or_fail(claimed);
+            total.unclaimed_balance.0 = total.unclaimed_balance.0.add_or_fail(account.unclaimed_amount(cur_ts));
+            total.forfeited_balance.0 = total.forfeited_balance.0.add_or_fail(account.forfeited_balance);
+        }
+        totals.into_iter().map(|entry| entry.1).collect()
+    }
+
+    /// Pending transfer requested by the account, if any.
+    pub fn get_account_transfer(&self, account_id: ValidAccountId) -> Option<AccountTransfer> {
+        self.account_transfers.get::<String>(&account_id.into()).cloned()
+    }
+
+    /// Whitelisted delegation targets with principal delegated to each.
+    pub fn get_delegations(&self) -> HashMap<AccountId, WrappedBalance> {
+        self.delegations.iter()
+        .map(|(contract_id, principal)| (contract_id.clone(), (*principal).into()))
+        .collect()
+    }
+
+    /// Principal recalled from each delegation target and not returned yet.
+    pub fn get_recalls(&self) -> HashMap<AccountId, WrappedBalance> {
+        self.recalled.iter()
+        .map(|(contract_id, amount)| (contract_id.clone(), (*amount).into()))
+        .collect()
+    }
+
+    /// Contracts whitelisted for `claim_and_call`, sorted.
+    pub fn get_claim_receivers(&self) -> Vec<AccountId> {
+        let mut receivers: Vec<AccountId> = self.claim_receivers.iter().cloned().collect();
+        receivers.sort();
+        receivers
+    }
+}
+#[cfg(test)]
+mod tests {
+    use near_sdk::json_types::U128;
+
+    use super::*;
+    use crate::test_utils::*;
+
+    fn balances(point: &ProjectionPoint) -> (TimestampSec, Balance, Balance, Balance, bool) {
+        (
+            point.timestamp,
+            point.locked_balance.0,
+            point.liquid_balance.0,
+            point.committed_balance.0,
+            point.shortfall,
+        )
+    }
+
+    #[test]
+    fn stats() {
+        let mut contract = setup();
+        set_context("owner", 155, 0);
+        let stats = contract.get_stats();
+        assert_eq!(stats.current_round, 5);
+        assert_eq!(stats.release_rounds, 10);
+        assert_eq!(stats.locked_balance.0, 500);
+        assert_eq!(stats.liquid_balance.0, 500);
+        assert_eq!(stats.unclaimed_balance.0, 250);
+        assert_eq!(stats.tranches[0].unlocked_balance.0, 500);
+
+        set_context("alice", 155, 0);
+        contract.claim();
+        let stats = contract.get_stats();
+        assert_eq!(stats.claimed_balance.0, 250);
+        assert_eq!(stats.liquid_balance.0, 250);
+        assert_eq!(stats.unclaimed_balance.0, 0);
+        assert_eq!(stats.in_flight_balance.0, 250);
+    }
+
+    #[test]
+    fn stats_round_down() {
+        set_context("owner", 115, 0);
+        let contract = Contract::new(id("owner"), id("token"), U128(1000), 100, 10, 3);
+        let stats = contract.get_stats();
+        assert_eq!(stats.current_round, 1);
+        assert_eq!(stats.locked_balance.0, 667);
+        assert_eq!(stats.liquid_balance.0, 333);
+        assert_eq!(stats.tranches[0].locked_balance.0, 667);
+    }
+
+    #[test]
+    fn account_output() {
+        let mut contract = setup();
+        set_context("owner", 1000, NEAR);
+        contract.add_account(id("bob"), 100, 10, 4, U128(25), None);
+        let alice = contract.get_account(id("alice")).unwrap();
+        assert_eq!(alice.unclaimed_amount.0, 500);
+        let bob = contract.get_account(id("bob")).unwrap();
+        assert_eq!(bob.release_interval, 10);
+        assert_eq!(bob.unclaimed_amount.0, 100);
+        assert!(contract.get_account(id("carol")).is_none());
+        assert_eq!(contract.list_accounts().len(), 2);
+    }
+
+    #[test]
+    fn projection() {
+        let mut contract = setup();
+        let points: Vec<_> = contract.get_projection(100, 130, 15).iter().map(balances).collect();
+        assert_eq!(
+            points,
+            vec![(100, 1000, 0, 0, false), (115, 900, 100, 50, false), (130, 700, 300, 150, false)]
+        );
+
+        println!("*** Past points are projected from the same state");
+        set_context("owner", 150, NEAR);
+        contract.add_account(id("bob"), 100, 10, 10, U128(60), None);
+        let points: Vec<_> = contract.get_projection(0, 1000, 500).iter().map(balances).collect();
+        assert_eq!(
+            points,
+            vec![(0, 1000, 0, 0, false), (500, 0, 1000, 1100, true), (1000, 0, 1000, 1100, true)]
+        );
+
+        println!("*** Points before claims already made saturate");
+        set_context("alice", 155, 0);
+        contract.claim();
+        let points: Vec<_> = contract.get_projection(100, 130, 30).iter().map(balances).collect();
+        assert_eq!(points, vec![(100, 1000, 0, 0, false), (130, 700, 50, 180, true)]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn stats_rounds_saturate() {
+        set_context("owner", 0, 0);
+        let tranche = |start_timestamp, release_rounds| Tranche {
+            start_timestamp,
+            release_interval: 1,
+            release_rounds,
+            total_balance: U128(100),
+            release_day: None,
+        };
+        let contract = Contract::new_with_tranches(
+            id("owner"),
+            id("token"),
+            vec![tranche(0, u32::MAX), tranche(u64::from(u32::MAX), 1)],
+        );
+        assert_eq!(contract.get_stats().release_rounds, u32::MAX);
+    }
+
+    #[test]
+    fn cost_center_totals() {
+        let mut contract = setup();
+        set_context("owner", 150, NEAR);
+        let metadata = AccountMetadata {
+            cost_center: Some("sales".to_string()),
+            ..Default::default()
+        };
+        contract.add_account(id("bob"), 100, 10, 4, U128(25), Some(metadata));
+        contract.terminate_account(id("bob"), 120);
+        set_context("alice", 155, 0);
+        contract.claim();
+
+        let totals = contract.get_cost_center_totals();
+        assert_eq!(totals.len(), 2);
+        assert_eq!(totals[0].cost_center, None);
+        assert_eq!(totals[0].total_balance.0, 500);
+        assert_eq!(totals[0].claimed_balance.0, 250);
+        assert_eq!(totals[0].unclaimed_balance.0, 0);
+        assert_eq!(totals[1].cost_center, Some("sales".to_string()));
+        assert_eq!(totals[1].accounts, 1);
+        assert_eq!(totals[1].total_balance.0, 50);
+        assert_eq!(totals[1].unclaimed_balance.0, 50);
+        assert_eq!(totals[1].forfeited_balance.0, 50);
+    }
+}
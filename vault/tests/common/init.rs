@@ -8,12 +8,14 @@ use near_sdk_sim::{
     call, deploy, init_simulator, to_yocto, view, ContractAccount, UserAccount,
 };
 
-use vault::{ContractContract as Vault, Stats, AccountOutput};
+use vault::{ContractContract as Vault, Stats, AccountOutput, Tranche};
 
 use test_token::ContractContract as TestToken;
+use test_staking::ContractContract as TestStaking;
 
 near_sdk_sim::lazy_static_include::lazy_static_include_bytes! {
     TEST_TOKEN_WASM_BYTES => "../res/test_token.wasm",
+    TEST_STAKING_WASM_BYTES => "../res/test_staking.wasm",
     VAULT_WASM_BYTES => "../res/vault_release.wasm",
 }
 
@@ -41,6 +43,21 @@ pub fn test_token(
     t
 }
 
+pub fn test_staking(
+    root: &UserAccount,
+    staking_id: AccountId,
+    token_id: AccountId,
+) -> ContractAccount<TestStaking> {
+    let s = deploy!(
+        contract: TestStaking,
+        contract_id: staking_id,
+        bytes: &TEST_STAKING_WASM_BYTES,
+        signer_account: root
+    );
+    call!(root, s.new(to_va(token_id))).assert_success();
+    s
+}
+
 pub fn balance_of(token: &ContractAccount<TestToken>, account_id: &AccountId) -> u128 {
     view!(token.ft_balance_of(to_va(account_id.clone())))
         .unwrap_json::<U128>()
@@ -65,7 +82,7 @@ pub fn to_va(a: AccountId) -> ValidAccountId {
     ValidAccountId::try_from(a).unwrap()
 }
 
-pub fn setup_vault(total: u128, start_at: u32, interval: u32, rounds: u32) -> (
+pub fn setup_vault(total: u128, start_at: u64, interval: u64, rounds: u32) -> (
     UserAccount,
     UserAccount,
     ContractAccount<Vault>,
@@ -93,3 +110,30 @@ pub fn setup_vault(total: u128, start_at: u32, interval: u32, rounds: u32) -> (
 
     (root, owner, vault, token)
 }
+
+pub fn setup_vault_with_tranches(tranches: Vec<Tranche>) -> (
+    UserAccount,
+    UserAccount,
+    ContractAccount<Vault>,
+    ContractAccount<TestToken>,
+) {
+    let total: u128 = tranches.iter().map(|tranche| tranche.total_balance.0).sum();
+    let root = init_simulator(None);
+    let owner = root.create_user("owner".to_string(), to_yocto("100"));
+    let vault = deploy!(
+        contract: Vault,
+        contract_id: "vault".to_string(),
+        bytes: &VAULT_WASM_BYTES,
+        signer_account: root,
+        init_method: new_with_tranches(
+            to_va("owner".to_string()), 
+            to_va("test_token".to_string()),
+            tranches
+        )
+    );
+    let token = test_token(&root, "test_token".to_string(), vec!["vault".to_string(), owner.account_id()]);
+
+    call!(owner, token.mint(U128(total))).assert_success();
+
+    (root, owner, vault, token)
+}
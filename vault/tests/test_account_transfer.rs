@@ -0,0 +1,74 @@
+use near_sdk::json_types::{U128};
+use near_sdk_sim::{
+    call, to_yocto, view,
+};
+use vault::{AccountOutput, AccountTransfer};
+use crate::common::init::*;
+
+pub mod common;
+
+
+#[test]
+fn rotate_beneficiary_wallet() {
+    let (root, owner, vault, token) = setup_vault(10000, 50, 10, 10);
+    call!(
+        owner,
+        token.ft_transfer(vault.valid_account_id(), U128(10000), None),
+        deposit = 1
+    ).assert_success();
+    let user1 = root.create_user("user1".to_string(), to_yocto("10"));
+    let user2 = root.create_user("user2".to_string(), to_yocto("10"));
+    let user3 = root.create_user("user3".to_string(), to_yocto("10"));
+    call!(owner, vault.add_account(user1.valid_account_id(), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"))
+    .assert_success();
+    call!(owner, vault.add_account(user3.valid_account_id(), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"))
+    .assert_success();
+    call!(user1, token.storage_deposit(None, None), deposit = to_yocto("1")).assert_success();
+    call!(user2, token.storage_deposit(None, None), deposit = to_yocto("1")).assert_success();
+
+    println!("*** Go beyond round 5, user1 claims");
+    assert!(root.borrow_runtime_mut().produce_blocks(100).is_ok());
+    call!(user1, vault.claim()).assert_success();
+    let user_info = view!(vault.get_account(user1.valid_account_id())).unwrap_json::<AccountOutput>();
+    let claimed_round = user_info.last_claim_round;
+    assert!(claimed_round > 0);
+
+    println!("*** Request to an existing account fails");
+    let out_come = call!(user1, vault.request_account_transfer(user3.valid_account_id()));
+    assert!(!out_come.is_ok());
+
+    println!("*** Request, can't accept before approval");
+    call!(user1, vault.request_account_transfer(user2.valid_account_id())).assert_success();
+    let transfer = view!(vault.get_account_transfer(user1.valid_account_id())).unwrap_json::<AccountTransfer>();
+    assert_eq!(transfer, AccountTransfer { new_account_id: user2.account_id(), approved: false });
+    let out_come = call!(user2, vault.accept_account_transfer(user1.valid_account_id()));
+    assert!(!out_come.is_ok());
+
+    println!("*** Only owner approves, only new wallet accepts");
+    let out_come = call!(user1, vault.approve_account_transfer(user1.valid_account_id()));
+    assert!(!out_come.is_ok());
+    call!(owner, vault.approve_account_transfer(user1.valid_account_id())).assert_success();
+    let out_come = call!(user3, vault.accept_account_transfer(user1.valid_account_id()));
+    assert!(!out_come.is_ok());
+    call!(user2, vault.accept_account_transfer(user1.valid_account_id())).assert_success();
+
+    println!("*** Progress moved to the new wallet");
+    assert!(view!(vault.get_account(user1.valid_account_id())).unwrap_json_value().is_null());
+    assert!(view!(vault.get_account_transfer(user1.valid_account_id())).unwrap_json_value().is_null());
+    let user_info = view!(vault.get_account(user2.valid_account_id())).unwrap_json::<AccountOutput>();
+    assert_eq!(user_info.last_claim_round, claimed_round);
+    let out_come = call!(user1, vault.claim());
+    assert!(!out_come.is_ok());
+
+    println!("*** New wallet claims the rest");
+    assert!(root.borrow_runtime_mut().produce_blocks(100).is_ok());
+    call!(user2, vault.claim()).assert_success();
+    assert_eq!(balance_of(&token, &user1.account_id()), 20 * claimed_round as u128);
+    assert_eq!(balance_of(&token, &user2.account_id()), 20 * (10 - claimed_round) as u128);
+
+    println!("*** Pending request can be cancelled");
+    call!(user2, vault.request_account_transfer(user1.valid_account_id())).assert_success();
+    call!(user2, vault.cancel_account_transfer(user2.valid_account_id())).assert_success();
+    let out_come = call!(owner, vault.approve_account_transfer(user2.valid_account_id()));
+    assert!(!out_come.is_ok());
+}
@@ -0,0 +1,74 @@
+use near_sdk::json_types::{U128};
+use near_sdk_sim::{
+    call, to_yocto, view,
+};
+use vault::{Stats, AccountOutput};
+use crate::common::init::*;
+
+pub mod common;
+
+
+#[test]
+fn claim_into_staking() {
+    let (root, owner, vault, token) = setup_vault(10000, 50, 10, 10);
+    call!(
+        owner,
+        token.ft_transfer(vault.valid_account_id(), U128(10000), None),
+        deposit = 1
+    ).assert_success();
+    let staking = test_staking(&root, "staking".to_string(), token.account_id());
+    call!(root, token.storage_deposit(Some(staking.valid_account_id()), None), deposit = to_yocto("1"))
+    .assert_success();
+
+    let user1 = root.create_user("user1".to_string(), to_yocto("10"));
+    call!(owner, vault.add_account(user1.valid_account_id(), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"))
+    .assert_success();
+
+    println!("*** Go beyond round 10, all released");
+    assert!(root.borrow_runtime_mut().produce_blocks(150).is_ok());
+
+    println!("*** Only whitelisted receivers, with msg naming the caller");
+    let out_come = call!(user1, vault.claim_and_call(staking.valid_account_id(), r#"{"account_id":"user1"}"#.to_string()));
+    assert!(format!("{:?}", out_come.status()).contains("ERR_NOT_CLAIM_RECEIVER"));
+    let out_come = call!(user1, vault.add_claim_receiver(staking.valid_account_id()));
+    assert!(format!("{:?}", out_come.status()).contains("ERR_NOT_ALLOWED"));
+    call!(owner, vault.add_claim_receiver(staking.valid_account_id())).assert_success();
+    let out_come = call!(user1, vault.claim_and_call(staking.valid_account_id(), r#"{"account_id":"owner"}"#.to_string()));
+    assert!(format!("{:?}", out_come.status()).contains("ERR_INVALID_CLAIM_MSG"));
+
+    println!("*** User1 claims into staking, which only takes 150");
+    call!(user1, vault.claim_and_call(staking.valid_account_id(), r#"{"account_id":"user1","limit":"150"}"#.to_string())).assert_success();
+    assert_eq!(balance_of(&token, &staking.account_id()), 150);
+    assert_eq!(view!(staking.get_staked(user1.valid_account_id())).unwrap_json::<U128>().0, 150);
+    assert_eq!(view!(staking.get_staked(vault.valid_account_id())).unwrap_json::<U128>().0, 0);
+    let user_info = view!(vault.get_account(user1.valid_account_id())).unwrap_json::<AccountOutput>();
+    assert_userinfo(&user_info, 10, 50);
+    assert_eq!(user_info.refunded_balance.0, 50);
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.claimed_balance.0, 150);
+    assert_eq!(vault_stats.unclaimed_balance.0, 50);
+
+    println!("*** Staking refuses the rest, still claimable");
+    call!(user1, vault.claim_and_call(staking.valid_account_id(), r#"{"account_id":"user1","limit":"refund"}"#.to_string())).assert_success();
+    assert_eq!(balance_of(&token, &staking.account_id()), 150);
+    let user_info = view!(vault.get_account(user1.valid_account_id())).unwrap_json::<AccountOutput>();
+    assert_userinfo(&user_info, 10, 50);
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.claimed_balance.0, 150);
+
+    println!("*** Transfer call to unregistered receiver is credited back as a whole");
+    call!(owner, vault.add_claim_receiver(to_va("nobody".to_string()))).assert_success();
+    call!(user1, vault.claim_and_call(to_va("nobody".to_string()), r#"{"account_id":"user1"}"#.to_string())).assert_success();
+    let user_info = view!(vault.get_account(user1.valid_account_id())).unwrap_json::<AccountOutput>();
+    assert_userinfo(&user_info, 10, 50);
+
+    println!("*** User1 claims the refunded balance to wallet");
+    call!(user1, token.storage_deposit(None, None), deposit = to_yocto("1")).assert_success();
+    call!(user1, vault.claim()).assert_success();
+    assert_eq!(balance_of(&token, &user1.account_id()), 50);
+    let user_info = view!(vault.get_account(user1.valid_account_id())).unwrap_json::<AccountOutput>();
+    assert_userinfo(&user_info, 10, 0);
+    assert_eq!(user_info.refunded_balance.0, 0);
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.claimed_balance.0, 200);
+}
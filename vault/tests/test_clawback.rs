@@ -0,0 +1,100 @@
+use near_sdk::json_types::{U128};
+use near_sdk_sim::{
+    call, to_yocto, view,
+};
+use vault::{AccountOutput, ClawbackMode, Stats};
+use crate::common::init::*;
+
+pub mod common;
+
+
+#[test]
+fn clawback_modes() {
+    let (root, owner, vault, token) = setup_vault(10000, 50, 10, 10);
+    call!(
+        owner,
+        token.ft_transfer(vault.valid_account_id(), U128(10000), None),
+        deposit = 1
+    ).assert_success();
+    let user1 = root.create_user("user1".to_string(), to_yocto("10"));
+    let user2 = root.create_user("user2".to_string(), to_yocto("10"));
+    let user3 = root.create_user("user3".to_string(), to_yocto("10"));
+    let treasury = root.create_user("treasury".to_string(), to_yocto("10"));
+    for user in [&user1, &user2, &user3].iter() {
+        call!(owner, vault.add_account(user.valid_account_id(), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"))
+        .assert_success();
+    }
+    call!(treasury, token.storage_deposit(None, None), deposit = to_yocto("1")).assert_success();
+    assert!(root.borrow_runtime_mut().produce_blocks(100).is_ok());
+
+    println!("*** Only owner claws back");
+    let out_come = call!(user1, vault.clawback(user1.valid_account_id(), ClawbackMode::UnvestedOnly, "misconduct".to_string(), None));
+    assert!(format!("{:?}", out_come.status()).contains("ERR_NOT_ALLOWED"));
+
+    println!("*** Unvested only, released rounds are still claimable");
+    let user_info = view!(vault.get_account(user1.valid_account_id())).unwrap_json::<AccountOutput>();
+    let released = user_info.unclaimed_amount.0 / 20;
+    assert!(released > 0 && released < 10);
+    let out_come = call!(owner, vault.clawback(user1.valid_account_id(), ClawbackMode::UnvestedOnly, "misconduct".to_string(), None));
+    out_come.assert_success();
+    let event = format!(
+        r#"EVENT_JSON:{{"standard":"vault","version":"1.0.0","event":"clawback","data":[{{"account_id":"user1","forfeited_amount":"{}","released_amount":"0","reason":"misconduct"}}]}}"#,
+        (10 - released) * 20
+    );
+    assert!(out_come.logs().contains(&event));
+    let user_info = view!(vault.get_account(user1.valid_account_id())).unwrap_json::<AccountOutput>();
+    assert_eq!(user_info.release_rounds as u128, released);
+    assert_eq!(user_info.forfeited_balance.0, (10 - released) * 20);
+    assert_eq!(user_info.unclaimed_amount.0, released * 20);
+    assert_eq!(user_info.clawback_reason, Some("misconduct".to_string()));
+
+    println!("*** Amount must be whole rounds not claimed yet");
+    let out_come = call!(owner, vault.clawback(user2.valid_account_id(), ClawbackMode::Amount(U128(30)), "misconduct".to_string(), None));
+    assert!(format!("{:?}", out_come.status()).contains("ERR_CLAWBACK_AMOUNT_NOT_IN_ROUNDS"));
+    let out_come = call!(owner, vault.clawback(user2.valid_account_id(), ClawbackMode::Amount(U128(220)), "misconduct".to_string(), None));
+    assert!(format!("{:?}", out_come.status()).contains("ERR_CLAWBACK_AMOUNT_OVER_UNCLAIMED"));
+    call!(owner, vault.clawback(user2.valid_account_id(), ClawbackMode::Amount(U128(40)), "misconduct".to_string(), None))
+    .assert_success();
+    let user_info = view!(vault.get_account(user2.valid_account_id())).unwrap_json::<AccountOutput>();
+    assert_eq!(user_info.release_rounds, 8);
+    assert_eq!(user_info.forfeited_balance.0, 40);
+
+    println!("*** Include unclaimed, released part goes to treasury");
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    let claimed = vault_stats.claimed_balance.0;
+    call!(owner, vault.clawback(user3.valid_account_id(), ClawbackMode::IncludeUnclaimed, "misconduct".to_string(), Some(treasury.valid_account_id())))
+    .assert_success();
+    let user_info = view!(vault.get_account(user3.valid_account_id())).unwrap_json::<AccountOutput>();
+    assert_eq!(user_info.release_rounds, 0);
+    assert_eq!(user_info.unclaimed_amount.0, 0);
+    assert_eq!(user_info.forfeited_balance.0, 200);
+    let vested = balance_of(&token, &treasury.account_id());
+    assert!(vested > 0);
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.claimed_balance.0, claimed + vested);
+    assert!(view!(vault.check_invariants()).unwrap_json::<Vec<String>>().is_empty());
+}
+
+#[test]
+fn clawback_to_treasury_rollback() {
+    let (root, owner, vault, token) = setup_vault(10000, 50, 10, 10);
+    call!(
+        owner,
+        token.ft_transfer(vault.valid_account_id(), U128(10000), None),
+        deposit = 1
+    ).assert_success();
+    let user1 = root.create_user("user1".to_string(), to_yocto("10"));
+    call!(owner, vault.add_account(user1.valid_account_id(), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"))
+    .assert_success();
+    assert!(root.borrow_runtime_mut().produce_blocks(100).is_ok());
+
+    println!("*** Treasury not registered, payment rolls back into liquidity");
+    let out_come = call!(owner, vault.clawback(user1.valid_account_id(), ClawbackMode::IncludeUnclaimed, "misconduct".to_string(), Some(to_va("treasury".to_string()))));
+    assert!(!out_come.unwrap_json::<bool>());
+    let user_info = view!(vault.get_account(user1.valid_account_id())).unwrap_json::<AccountOutput>();
+    assert_eq!(user_info.forfeited_balance.0, 200);
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.claimed_balance.0, 0);
+    assert_eq!(vault_stats.in_flight_balance.0, 0);
+    assert_eq!(vault_stats.unclaimed_balance.0, 0);
+}
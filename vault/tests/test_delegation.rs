@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use near_sdk::json_types::{U128};
+use near_sdk_sim::{
+    call, to_yocto, view,
+};
+use vault::Stats;
+use crate::common::init::*;
+
+pub mod common;
+
+
+#[test]
+fn delegate_locked_balance() {
+    let (root, owner, vault, token) = setup_vault(10000, 50, 10, 10);
+    call!(
+        owner,
+        token.ft_transfer(vault.valid_account_id(), U128(10000), None),
+        deposit = 1
+    ).assert_success();
+    let staking = test_staking(&root, "staking".to_string(), token.account_id());
+    call!(root, token.storage_deposit(Some(staking.valid_account_id()), None), deposit = to_yocto("1"))
+    .assert_success();
+    let user1 = root.create_user("user1".to_string(), to_yocto("10"));
+
+    println!("*** Only whitelisted target within limit");
+    let out_come = call!(owner, vault.delegate(staking.valid_account_id(), U128(1000), "".to_string()));
+    assert!(!out_come.is_ok());
+    call!(owner, vault.add_delegation_target(staking.valid_account_id())).assert_success();
+    let out_come = call!(owner, vault.delegate(staking.valid_account_id(), U128(1000), "".to_string()));
+    let ex_status = format!("{:?}", out_come.promise_errors()[0].as_ref().unwrap().status());
+    assert!(ex_status.contains("ERR_DELEGATION_EXCEEDS_LIMIT"));
+    let out_come = call!(user1, vault.set_delegation_limit(5000));
+    assert!(!out_come.is_ok());
+    call!(owner, vault.set_delegation_limit(5000)).assert_success();
+
+    println!("*** Principal refused by target is taken back");
+    call!(owner, vault.delegate(staking.valid_account_id(), U128(1000), "refund".to_string())).assert_success();
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.delegated_balance.0, 0);
+    assert_eq!(balance_of(&token, &vault.account_id()), 10000);
+
+    println!("*** Delegate 4000 of locked balance");
+    call!(owner, vault.delegate(staking.valid_account_id(), U128(4000), "".to_string())).assert_success();
+    assert_eq!(view!(staking.get_staked(vault.valid_account_id())).unwrap_json::<U128>().0, 4000);
+    let delegations = view!(vault.get_delegations()).unwrap_json::<HashMap<String, U128>>();
+    assert_eq!(delegations.get(&staking.account_id()).unwrap().0, 4000);
+    let out_come = call!(owner, vault.delegate(staking.valid_account_id(), U128(1001), "".to_string()));
+    assert!(!out_come.is_ok());
+    let out_come = call!(owner, vault.remove_delegation_target(staking.valid_account_id()));
+    assert!(!out_come.is_ok());
+
+    println!("*** Go beyond round 10, delegated principal is out of liquidity");
+    assert!(root.borrow_runtime_mut().produce_blocks(150).is_ok());
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.locked_balance.0, 0);
+    assert_eq!(vault_stats.liquid_balance.0, 6000);
+    let out_come = call!(owner, vault.payment(owner.valid_account_id(), U128(6001)));
+    assert!(!out_come.is_ok());
+
+    println!("*** Anyone recalls exceeding principal, with yield");
+    call!(owner, token.mint(U128(200))).assert_success();
+    call!(owner, token.ft_transfer(staking.valid_account_id(), U128(200), None), deposit = 1).assert_success();
+    call!(root, staking.set_reward(U128(100))).assert_success();
+    let out_come = call!(user1, vault.recall_delegation(staking.valid_account_id(), U128(4001)));
+    assert!(!out_come.is_ok());
+    call!(user1, vault.recall_delegation(staking.valid_account_id(), U128(2000))).assert_success();
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.delegated_balance.0, 2000);
+    assert_eq!(vault_stats.delegation_yield.0, 100);
+    assert_eq!(vault_stats.liquid_balance.0, 8000);
+    assert_eq!(balance_of(&token, &vault.account_id()), 8100);
+    let recalls = view!(vault.get_recalls()).unwrap_json::<HashMap<String, U128>>();
+    assert!(recalls.is_empty());
+    let out_come = call!(user1, vault.recall_delegation(staking.valid_account_id(), U128(1)));
+    assert!(!out_come.is_ok());
+
+    println!("*** Owner recalls the rest");
+    call!(owner, vault.recall_delegation(staking.valid_account_id(), U128(2000))).assert_success();
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.delegated_balance.0, 0);
+    assert_eq!(vault_stats.delegation_yield.0, 200);
+    assert_eq!(vault_stats.liquid_balance.0, 10000);
+
+    println!("*** Owner withdraws yield and removes target");
+    let out_come = call!(user1, vault.withdraw_delegation_yield(user1.valid_account_id(), U128(200)));
+    assert!(!out_come.is_ok());
+    call!(owner, vault.withdraw_delegation_yield(owner.valid_account_id(), U128(200))).assert_success();
+    assert_eq!(balance_of(&token, &owner.account_id()), 200);
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.delegation_yield.0, 0);
+    call!(owner, vault.remove_delegation_target(staking.valid_account_id())).assert_success();
+    let delegations = view!(vault.get_delegations()).unwrap_json::<HashMap<String, U128>>();
+    assert!(delegations.is_empty());
+}
@@ -0,0 +1,81 @@
+use near_sdk::json_types::{U128};
+use near_sdk_sim::{
+    call, to_yocto, view,
+};
+use vault::{Stats, AccountOutput};
+use crate::common::init::*;
+
+pub mod common;
+
+
+#[test]
+fn keeper_distribute() {
+    let (root, owner, vault, token) = setup_vault(10000, 50, 10, 10);
+    call!(
+        owner,
+        token.ft_transfer(vault.valid_account_id(), U128(10000), None),
+        deposit = 1
+    ).assert_success();
+
+    let keeper = root.create_user("keeper".to_string(), to_yocto("10"));
+    call!(keeper, token.storage_deposit(None, None), deposit = to_yocto("1")).assert_success();
+    let mut users = vec![];
+    for name in vec!["user1", "user2", "user3"] {
+        let user = root.create_user(name.to_string(), to_yocto("10"));
+        call!(user, token.storage_deposit(None, None), deposit = to_yocto("1")).assert_success();
+        call!(owner, vault.add_account(user.valid_account_id(), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"))
+        .assert_success();
+        users.push(user);
+    }
+
+    println!("*** User3 opts out of push");
+    call!(users[2], vault.set_push_disabled(true)).assert_success();
+    let user_info = view!(vault.get_account(users[2].valid_account_id())).unwrap_json::<AccountOutput>();
+    assert!(user_info.push_disabled);
+
+    println!("*** Only owner can set keeper reward");
+    let out_come = call!(keeper, vault.set_keeper_reward(U128(1)));
+    assert!(!out_come.is_ok());
+    call!(owner, vault.set_keeper_reward(U128(1))).assert_success();
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.keeper_reward.0, 1);
+
+    println!("*** Go beyond round 10, all released");
+    assert!(root.borrow_runtime_mut().produce_blocks(150).is_ok());
+
+    println!("*** Keeper distributes the first account");
+    let out_come = call!(keeper, vault.distribute(0, 1));
+    out_come.assert_success();
+    assert_eq!(out_come.unwrap_json::<u64>(), 1);
+    assert_eq!(balance_of(&token, &users[0].account_id()), 200);
+    assert_eq!(balance_of(&token, &keeper.account_id()), 1);
+
+    println!("*** Keeper distributes the rest, skipping user3");
+    let out_come = call!(keeper, vault.distribute(1, 10));
+    out_come.assert_success();
+    assert_eq!(out_come.unwrap_json::<u64>(), 3);
+    assert_eq!(balance_of(&token, &users[1].account_id()), 200);
+    assert_eq!(balance_of(&token, &users[2].account_id()), 0);
+    assert_eq!(balance_of(&token, &keeper.account_id()), 2);
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.claimed_balance.0, 402);
+    assert_eq!(vault_stats.unclaimed_balance.0, 200);
+
+    println!("*** Nothing to distribute, no reward");
+    let out_come = call!(keeper, vault.distribute(0, 10));
+    out_come.assert_success();
+    assert_eq!(out_come.unwrap_json::<u64>(), 3);
+    assert_eq!(balance_of(&token, &keeper.account_id()), 2);
+
+    println!("*** Transfer to unregistered user fails, no reward");
+    let user4 = root.create_user("user4".to_string(), to_yocto("10"));
+    call!(owner, vault.add_account(user4.valid_account_id(), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"))
+    .assert_success();
+    let out_come = call!(keeper, vault.distribute(3, 10));
+    out_come.assert_success();
+    assert_eq!(out_come.unwrap_json::<u64>(), 4);
+    assert_eq!(balance_of(&token, &user4.account_id()), 0);
+    assert_eq!(balance_of(&token, &keeper.account_id()), 2);
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.claimed_balance.0, 402);
+}
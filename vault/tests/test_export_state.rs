@@ -0,0 +1,63 @@
+use near_sdk::json_types::{U128};
+use near_sdk_sim::{
+    call, to_yocto, view,
+};
+use vault::{Stats, StateSnapshot, SNAPSHOT_FORMAT};
+use crate::common::init::*;
+
+pub mod common;
+
+
+#[test]
+fn export_state_in_pages() {
+    let (root, owner, vault, token) = setup_vault(10000, 50, 10, 10);
+    call!(
+        owner,
+        token.ft_transfer(vault.valid_account_id(), U128(10000), None),
+        deposit = 1
+    ).assert_success();
+    let user1 = root.create_user("user1".to_string(), to_yocto("10"));
+    let user2 = root.create_user("user2".to_string(), to_yocto("10"));
+    let user3 = root.create_user("user3".to_string(), to_yocto("10"));
+    for user in vec![&user3, &user1, &user2] {
+        call!(owner, vault.add_account(user.valid_account_id(), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"))
+        .assert_success();
+    }
+    call!(user1, token.storage_deposit(None, None), deposit = to_yocto("1")).assert_success();
+
+    println!("*** Pages are sorted by account id and share the state hash");
+    let page1 = view!(vault.export_state(0, 2)).unwrap_json::<StateSnapshot>();
+    let page2 = view!(vault.export_state(2, 2)).unwrap_json::<StateSnapshot>();
+    assert_eq!(page1.header.format, SNAPSHOT_FORMAT);
+    assert_eq!(page1.header.total_accounts, 3);
+    assert_eq!(page1.header.owner_id, owner.account_id());
+    assert_eq!(page1.header.total_balance.0, 10000);
+    assert_eq!(page1.from_index, 0);
+    assert_eq!(
+        page1.accounts.iter().map(|account| account.account_id.clone()).collect::<Vec<_>>(),
+        vec![user1.account_id(), user2.account_id()]
+    );
+    assert_eq!(page2.from_index, 2);
+    assert_eq!(page2.accounts.len(), 1);
+    assert_eq!(page2.accounts[0].account_id, user3.account_id());
+    assert_eq!(page2.accounts[0].release_per_round.0, 20);
+    assert!(page2.accounts[0].storage_balance.0 > 0);
+    assert_eq!(page1.header, page2.header);
+    assert_eq!(page1.state_hash, page2.state_hash);
+    let empty = view!(vault.export_state(3, 2)).unwrap_json::<StateSnapshot>();
+    assert!(empty.accounts.is_empty());
+    assert_eq!(empty.state_hash, page1.state_hash);
+
+    println!("*** Time alone doesn't change the state");
+    assert!(root.borrow_runtime_mut().produce_blocks(100).is_ok());
+    let page = view!(vault.export_state(0, 10)).unwrap_json::<StateSnapshot>();
+    assert_eq!(page.state_hash, page1.state_hash);
+
+    println!("*** A claim changes the hash");
+    call!(user1, vault.claim()).assert_success();
+    let page = view!(vault.export_state(0, 10)).unwrap_json::<StateSnapshot>();
+    assert_ne!(page.state_hash, page1.state_hash);
+    let stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(page.header.claimed_balance, stats.claimed_balance);
+    assert!(page.accounts[0].last_claim_round > 0);
+}
@@ -0,0 +1,104 @@
+use near_sdk::json_types::{U128};
+use near_sdk_sim::{
+    call, to_yocto, view,
+};
+use crate::common::init::*;
+
+pub mod common;
+
+/// xorshift, so that a failing sequence can be replayed from its seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
+const STEPS: usize = 60;
+
+fn run_sequence(seed: u64) {
+    let mut rng = Rng(seed);
+    let (root, owner, vault, token) = setup_vault(10000, 50, 10, 10);
+    call!(owner, token.mint(U128(100000))).assert_success();
+    call!(
+        owner,
+        token.ft_transfer(vault.valid_account_id(), U128(10000), None),
+        deposit = 1
+    ).assert_success();
+    let users: Vec<_> = (0..4)
+        .map(|index| root.create_user(format!("user{}", index), to_yocto("10")))
+        .collect();
+    // the last user never registers to the token, so its claims fail and roll back
+    for user in users.iter().take(3) {
+        call!(user, token.storage_deposit(None, None), deposit = to_yocto("1")).assert_success();
+    }
+    assert!(view!(vault.check_invariants()).unwrap_json::<Vec<String>>().is_empty());
+
+    for step in 0..STEPS {
+        let user = &users[rng.below(users.len() as u64) as usize];
+        let op = rng.below(6);
+        match op {
+            0 => {
+                call!(
+                    owner,
+                    vault.add_account(
+                        user.valid_account_id(),
+                        50 + rng.below(100),
+                        10,
+                        1 + rng.below(10) as u32,
+                        U128(1 + rng.below(100) as u128),
+                        None
+                    ),
+                    deposit = to_yocto("0.01")
+                );
+            }
+            1 => {
+                call!(user, vault.claim());
+            }
+            2 => {
+                call!(owner, vault.payment(user.valid_account_id(), U128(1 + rng.below(200) as u128)));
+            }
+            3 => {
+                call!(owner, vault.remove_account(user.valid_account_id()));
+            }
+            4 => {
+                assert!(root.borrow_runtime_mut().produce_blocks(1 + rng.below(30)).is_ok());
+            }
+            _ => {
+                let extend_rounds = rng.below(3);
+                call!(
+                    owner,
+                    token.ft_transfer_call(
+                        vault.valid_account_id(),
+                        U128(1 + rng.below(500) as u128),
+                        None,
+                        format!("{{\"TopUp\": {{\"extend_rounds\": {}}}}}", extend_rounds)
+                    ),
+                    deposit = 1
+                );
+            }
+        }
+        let violations = view!(vault.check_invariants()).unwrap_json::<Vec<String>>();
+        assert!(
+            violations.is_empty(),
+            "seed {} step {} op {}: {:?}",
+            seed, step, op, violations
+        );
+    }
+}
+
+#[test]
+fn invariants_hold_over_random_operations() {
+    for seed in &[1, 42, 2021, 0xdead_beef, 0x1234_5678_9abc] {
+        println!("*** Sequence of seed {}", seed);
+        run_sequence(*seed);
+    }
+}
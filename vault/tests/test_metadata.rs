@@ -0,0 +1,84 @@
+use near_sdk::json_types::{U128};
+use near_sdk_sim::{
+    call, to_yocto, view,
+};
+use vault::{AccountMetadata, AccountOutput, CostCenterOutput, Stats};
+use crate::common::init::*;
+
+pub mod common;
+
+fn cost_center(cost_center: &str) -> AccountMetadata {
+    AccountMetadata {
+        cost_center: Some(cost_center.to_string()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn metadata_and_cost_centers() {
+    let (root, owner, vault, token) = setup_vault(10000, 50, 10, 10);
+    call!(
+        owner,
+        token.ft_transfer(vault.valid_account_id(), U128(10000), None),
+        deposit = 1
+    ).assert_success();
+    let user1 = root.create_user("user1".to_string(), to_yocto("10"));
+    let user2 = root.create_user("user2".to_string(), to_yocto("10"));
+    let user3 = root.create_user("user3".to_string(), to_yocto("10"));
+    let metadata = AccountMetadata {
+        label: Some("Alice".to_string()),
+        cost_center: Some("engineering".to_string()),
+        role: Some("developer".to_string()),
+        external_ref: Some([7u8; 32].into()),
+    };
+    call!(owner, vault.add_account(user1.valid_account_id(), 50, 10, 10, U128(20), Some(metadata.clone())), deposit = to_yocto("0.01"))
+    .assert_success();
+    call!(owner, vault.add_account(user2.valid_account_id(), 50, 10, 10, U128(30), Some(cost_center("engineering"))), deposit = to_yocto("0.01"))
+    .assert_success();
+    call!(owner, vault.add_account(user3.valid_account_id(), 50, 10, 10, U128(10), None), deposit = to_yocto("0.01"))
+    .assert_success();
+    let user_info = view!(vault.get_account(user1.valid_account_id())).unwrap_json::<AccountOutput>();
+    assert_eq!(user_info.metadata, metadata);
+
+    println!("*** Text fields are bounded");
+    let out_come = call!(
+        owner,
+        vault.update_account_metadata(user3.valid_account_id(), AccountMetadata {
+            label: Some("x".repeat(65)),
+            ..Default::default()
+        })
+    );
+    assert!(format!("{:?}", out_come.status()).contains("ERR_METADATA_TOO_LONG"));
+
+    println!("*** Only owner updates, the record storage is recharged");
+    let out_come = call!(user3, vault.update_account_metadata(user3.valid_account_id(), cost_center("sales")));
+    assert!(format!("{:?}", out_come.status()).contains("ERR_NOT_ALLOWED"));
+    let pool = view!(vault.get_stats()).unwrap_json::<Stats>().storage_pool_balance.0;
+    let staked = view!(vault.storage_balance_of(user3.valid_account_id())).unwrap_json_value()["total"].as_str().unwrap().parse::<u128>().unwrap();
+    call!(owner, vault.update_account_metadata(user3.valid_account_id(), cost_center("sales"))).assert_success();
+    let staked_after = view!(vault.storage_balance_of(user3.valid_account_id())).unwrap_json_value()["total"].as_str().unwrap().parse::<u128>().unwrap();
+    assert!(staked_after > staked);
+    let pool_after = view!(vault.get_stats()).unwrap_json::<Stats>().storage_pool_balance.0;
+    assert_eq!(pool - pool_after, staked_after - staked);
+    call!(owner, vault.update_account_metadata(user3.valid_account_id(), AccountMetadata::default())).assert_success();
+    let staked_after = view!(vault.storage_balance_of(user3.valid_account_id())).unwrap_json_value()["total"].as_str().unwrap().parse::<u128>().unwrap();
+    assert_eq!(staked_after, staked);
+    call!(owner, vault.update_account_metadata(user3.valid_account_id(), cost_center("sales"))).assert_success();
+
+    println!("*** Totals by cost center");
+    assert!(root.borrow_runtime_mut().produce_blocks(100).is_ok());
+    call!(user1, token.storage_deposit(None, None), deposit = to_yocto("1")).assert_success();
+    call!(user1, vault.claim()).assert_success();
+    let claimed = balance_of(&token, &user1.account_id());
+    let totals = view!(vault.get_cost_center_totals()).unwrap_json::<Vec<CostCenterOutput>>();
+    assert_eq!(totals.len(), 2);
+    assert_eq!(totals[0].cost_center, Some("engineering".to_string()));
+    assert_eq!(totals[0].accounts, 2);
+    assert_eq!(totals[0].total_balance.0, 500);
+    assert_eq!(totals[0].claimed_balance.0, claimed);
+    let user2_info = view!(vault.get_account(user2.valid_account_id())).unwrap_json::<AccountOutput>();
+    assert_eq!(totals[0].unclaimed_balance.0, user2_info.unclaimed_amount.0);
+    assert_eq!(totals[1].cost_center, Some("sales".to_string()));
+    assert_eq!(totals[1].accounts, 1);
+    assert_eq!(totals[1].total_balance.0, 100);
+}
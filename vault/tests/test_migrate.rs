@@ -1,8 +1,9 @@
-use near_sdk::json_types::{U128};
-use near_sdk_sim::{call, deploy, view, init_simulator, to_yocto};
-use vault::{ContractContract as Vault, Stats};
+use near_sdk::json_types::{Base58CryptoHash, U128};
+use near_sdk_sim::{call, deploy, view, init_simulator, to_yocto, ContractAccount, UserAccount};
+use vault::{AccountOutput, ContractContract as Vault, Stats};
 
 near_sdk_sim::lazy_static_include::lazy_static_include_bytes! {
+    V000_VAULT_WASM_BYTES => "../res/vault_v000.wasm",
     PREV_VAULT_WASM_BYTES => "../res/vault_v030.wasm",
     VAULT_WASM_BYTES => "../res/vault_release.wasm",
 }
@@ -11,6 +12,27 @@ near_sdk_sim::lazy_static_include::lazy_static_include_bytes! {
 use crate::common::init::*;
 pub mod common;
 
+const UPGRADE_DELAY: u64 = 24 * 3600;
+
+fn stage_code(owner: &UserAccount, vault: &ContractAccount<Vault>, code: &[u8]) -> Base58CryptoHash {
+    owner.call(
+        vault.user_account.account_id.clone(),
+        "stage_code",
+        code,
+        near_sdk_sim::DEFAULT_GAS,
+        0,
+    )
+    .assert_success();
+    view!(vault.get_staged_code_hash()).unwrap_json::<Option<Base58CryptoHash>>().unwrap()
+}
+
+/// Stages the code and deploys it once the delay passed.
+fn stage_and_deploy(root: &UserAccount, owner: &UserAccount, vault: &ContractAccount<Vault>, code: &[u8]) {
+    let code_hash = stage_code(owner, vault, code);
+    assert!(root.borrow_runtime_mut().produce_blocks(UPGRADE_DELAY + 1).is_ok());
+    call!(owner, vault.deploy_staged_code(code_hash)).assert_success();
+}
+
 #[test]
 fn test_upgrade() {
     let root = init_simulator(None);
@@ -70,6 +92,55 @@ fn test_upgrade() {
     println!("{}", vault_stats.version);
 
     // Upgrade to the same code migration is skipped.
+    stage_and_deploy(&root, &owner, &vault, &VAULT_WASM_BYTES);
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    println!("{}", vault_stats.version);
+
+    println!("*** Accounts and balances survive");
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.total_balance.0, 10000);
+    assert_eq!(vault_stats.owner_id, owner.account_id());
+    assert_eq!(vault_stats.tranches.len(), 1);
+    assert_eq!(vault_stats.tranches[0].release_rounds, 10);
+    call!(owner, vault.add_account(to_va("user1".to_string()), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"))
+    .assert_success();
+    let user_info = view!(vault.get_account(to_va("user1".to_string()))).unwrap_json::<AccountOutput>();
+    assert_eq!(user_info.release_per_round.0, 20);
+}
+
+#[test]
+fn test_upgrade_from_v000() {
+    let root = init_simulator(None);
+    let owner = root.create_user("owner".to_string(), to_yocto("100"));
+    let vault = deploy!(
+        contract: Vault,
+        contract_id: "vault".to_string(),
+        bytes: &V000_VAULT_WASM_BYTES,
+        signer_account: root,
+        init_method: new(
+            to_va("owner".to_string()), 
+            to_va("test_token".to_string()),
+            U128(10000),  // total balance
+            50,  // start timestamp
+            10,  // release interval
+            10  // release round
+        )
+    );
+    let token = test_token(&root, "test_token".to_string(), vec!["vault".to_string(), owner.account_id()]);
+    call!(owner, token.mint(U128(10000))).assert_success();
+    call!(
+        owner,
+        token.ft_transfer(to_va("vault".to_string()), U128(10000), None),
+        deposit = 1
+    ).assert_success();
+
+    println!("*** 0.0.0 is frozen");
+    let vault_stats = view!(vault.get_stats()).unwrap_json_value();
+    assert_eq!(vault_stats["version"], "0.0.0");
+    let out_come = call!(owner, vault.add_account(to_va("user1".to_string()), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"));
+    assert!(!out_come.is_ok());
+
+    println!("*** Upgrade to current");
     owner.call(
         vault.user_account.account_id.clone(),
         "upgrade",
@@ -79,6 +150,76 @@ fn test_upgrade() {
     )
     .assert_success();
     let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
-    println!("{}", vault_stats.version);
+    assert_eq!(vault_stats.total_balance.0, 10000);
+    assert_eq!(vault_stats.claimed_balance.0, 0);
+    assert_eq!(vault_stats.tranches.len(), 1);
+    call!(owner, vault.add_account(to_va("user1".to_string()), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"))
+    .assert_success();
+
+    println!("*** Upgrade again from the stored version");
+    stage_and_deploy(&root, &owner, &vault, &VAULT_WASM_BYTES);
+    let user_info = view!(vault.get_account(to_va("user1".to_string()))).unwrap_json::<AccountOutput>();
+    assert_eq!(user_info.release_per_round.0, 20);
+}
+
+#[test]
+fn test_staged_upgrade() {
+    let (root, owner, vault, _token) = setup_vault(10000, 50, 10, 10);
+    let user1 = root.create_user("user1".to_string(), to_yocto("10"));
 
-}
\ No newline at end of file
+    println!("*** Only owner stages code");
+    let result = user1.call(
+        vault.user_account.account_id.clone(),
+        "stage_code",
+        &VAULT_WASM_BYTES,
+        near_sdk_sim::DEFAULT_GAS,
+        0,
+    );
+    assert!(!result.is_ok());
+    assert!(view!(vault.get_staged_code_hash()).unwrap_json_value().is_null());
+    let code_hash = stage_code(&owner, &vault, &VAULT_WASM_BYTES);
+
+    println!("*** Deploy needs the delay and the right hash");
+    let out_come = call!(owner, vault.deploy_staged_code(code_hash));
+    assert!(format!("{:?}", out_come.status()).contains("ERR_UPGRADE_DELAY"));
+    assert!(root.borrow_runtime_mut().produce_blocks(UPGRADE_DELAY + 1).is_ok());
+    let prev_hash = stage_code(&owner, &vault, &PREV_VAULT_WASM_BYTES);
+    let out_come = call!(owner, vault.deploy_staged_code(code_hash));
+    assert!(format!("{:?}", out_come.status()).contains("ERR_CODE_HASH_MISMATCH"));
+
+    println!("*** Restaging restarts the delay");
+    let code_hash = stage_code(&owner, &vault, &VAULT_WASM_BYTES);
+    let out_come = call!(owner, vault.deploy_staged_code(code_hash));
+    assert!(!out_come.is_ok());
+    assert!(root.borrow_runtime_mut().produce_blocks(UPGRADE_DELAY + 1).is_ok());
+    let out_come = call!(owner, vault.deploy_staged_code(code_hash));
+    out_come.assert_success();
+    assert!(out_come.unwrap_json::<bool>());
+    assert!(view!(vault.get_staged_code_hash()).unwrap_json_value().is_null());
+    assert!(view!(vault.get_rollback_code_hash()).unwrap_json_value().is_null());
+
+    println!("*** Failed migration drops the staged code, the running code keeps its hashes");
+    assert_ne!(prev_hash, code_hash);
+    stage_code(&owner, &vault, &PREV_VAULT_WASM_BYTES);
+    assert!(root.borrow_runtime_mut().produce_blocks(UPGRADE_DELAY + 1).is_ok());
+    let out_come = call!(owner, vault.deploy_staged_code(prev_hash));
+    assert!(!out_come.unwrap_json::<bool>());
+    assert!(view!(vault.get_staged_code_hash()).unwrap_json_value().is_null());
+    assert_eq!(
+        view!(vault.get_deployed_code_hash()).unwrap_json::<Option<Base58CryptoHash>>(),
+        Some(code_hash)
+    );
+    assert!(view!(vault.get_rollback_code_hash()).unwrap_json_value().is_null());
+
+    println!("*** The replaced code waits for the delay too");
+    stage_and_deploy(&root, &owner, &vault, &VAULT_WASM_BYTES);
+    assert_eq!(
+        view!(vault.get_rollback_code_hash()).unwrap_json::<Option<Base58CryptoHash>>(),
+        Some(code_hash)
+    );
+    stage_code(&owner, &vault, &VAULT_WASM_BYTES);
+    let out_come = call!(owner, vault.deploy_staged_code(code_hash));
+    assert!(format!("{:?}", out_come.status()).contains("ERR_UPGRADE_DELAY"));
+    assert!(root.borrow_runtime_mut().produce_blocks(UPGRADE_DELAY + 1).is_ok());
+    call!(owner, vault.deploy_staged_code(code_hash)).assert_success();
+}
@@ -0,0 +1,50 @@
+use near_sdk::json_types::{U128};
+use near_sdk_sim::{
+    call, to_yocto, view,
+};
+use vault::{AccountOutput, Stats, Tranche};
+use crate::common::init::*;
+
+pub mod common;
+
+#[test]
+fn monthly_account() {
+    let (root, owner, vault, token) = setup_vault(10000, 50, 10, 10);
+    call!(
+        owner,
+        token.ft_transfer(vault.valid_account_id(), U128(10000), None),
+        deposit = 1
+    ).assert_success();
+    let user1 = root.create_user("user1".to_string(), to_yocto("10"));
+
+    println!("*** Release day is from 1 to 31");
+    let out_come = call!(owner, vault.add_monthly_account(user1.valid_account_id(), 50, 0, 12, U128(10), None), deposit = to_yocto("0.01"));
+    assert!(format!("{:?}", out_come.status()).contains("ERR_INVALID_RELEASE_DAY"));
+    let out_come = call!(owner, vault.add_monthly_account(user1.valid_account_id(), 50, 32, 12, U128(10), None), deposit = to_yocto("0.01"));
+    assert!(format!("{:?}", out_come.status()).contains("ERR_INVALID_RELEASE_DAY"));
+    let out_come = call!(user1, vault.add_monthly_account(user1.valid_account_id(), 50, 31, 12, U128(10), None), deposit = to_yocto("0.01"));
+    assert!(format!("{:?}", out_come.status()).contains("ERR_NOT_ALLOWED"));
+
+    call!(owner, vault.add_monthly_account(user1.valid_account_id(), 50, 31, 12, U128(10), None), deposit = to_yocto("0.01"))
+    .assert_success();
+    let user_info = view!(vault.get_account(user1.valid_account_id())).unwrap_json::<AccountOutput>();
+    assert_eq!(user_info.release_day, Some(31));
+    assert_eq!(user_info.release_rounds, 12);
+
+    println!("*** Nothing released before the first month day");
+    // sim time starts at 1970-01-01, the first round ends on Jan 31
+    assert!(root.borrow_runtime_mut().produce_blocks(100).is_ok());
+    let user_info = view!(vault.get_account(user1.valid_account_id())).unwrap_json::<AccountOutput>();
+    assert_eq!(user_info.unclaimed_amount.0, 0);
+}
+
+#[test]
+fn monthly_tranche() {
+    let (_root, _owner, vault, _token) = setup_vault_with_tranches(vec![
+        Tranche { start_timestamp: 50, release_interval: 0, release_rounds: 12, total_balance: U128(1200), release_day: Some(1) },
+    ]);
+    let stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(stats.tranches[0].release_day, Some(1));
+    assert_eq!(stats.current_round, 0);
+    assert_eq!(stats.locked_balance.0, 1200);
+}
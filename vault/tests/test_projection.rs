@@ -0,0 +1,60 @@
+use near_sdk::json_types::{U128};
+use near_sdk_sim::{
+    call, to_yocto, view,
+};
+use vault::ProjectionPoint;
+use crate::common::init::*;
+
+pub mod common;
+
+
+#[test]
+fn project_balances() {
+    let (root, owner, vault, token) = setup_vault(10000, 50, 10, 10);
+    call!(
+        owner,
+        token.ft_transfer(vault.valid_account_id(), U128(10000), None),
+        deposit = 1
+    ).assert_success();
+    let user1 = root.create_user("user1".to_string(), to_yocto("10"));
+    let user2 = root.create_user("user2".to_string(), to_yocto("10"));
+    call!(owner, vault.add_account(user1.valid_account_id(), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"))
+    .assert_success();
+
+    println!("*** Project the whole schedule");
+    let points = view!(vault.get_projection(50, 150, 10)).unwrap_json::<Vec<ProjectionPoint>>();
+    assert_eq!(points.len(), 11);
+    for (index, point) in points.iter().enumerate() {
+        let round = index as u128;
+        assert_eq!(point.timestamp, 50 + 10 * index as u64);
+        assert_eq!(point.locked_balance.0, 10000 - 1000 * round);
+        assert_eq!(point.liquid_balance.0, 1000 * round);
+        assert_eq!(point.committed_balance.0, 20 * round);
+        assert!(!point.shortfall);
+    }
+
+    println!("*** Flag timestamps where obligations exceed liquidity");
+    call!(owner, vault.add_account(user2.valid_account_id(), 100, 10, 4, U128(2500), None), deposit = to_yocto("0.01"))
+    .assert_success();
+    let points = view!(vault.get_projection(90, 150, 20)).unwrap_json::<Vec<ProjectionPoint>>();
+    assert_eq!(points.len(), 4);
+    // user2 starts at 100, 2500 is due each 10s
+    assert_eq!(points[0].committed_balance.0, 80);
+    assert!(!points[0].shortfall);
+    assert_eq!(points[1].liquid_balance.0, 6000);
+    assert_eq!(points[1].committed_balance.0, 120 + 2500);
+    assert!(!points[1].shortfall);
+    assert_eq!(points[2].liquid_balance.0, 8000);
+    assert_eq!(points[2].committed_balance.0, 160 + 7500);
+    assert!(!points[2].shortfall);
+    assert_eq!(points[3].liquid_balance.0, 10000);
+    assert_eq!(points[3].committed_balance.0, 200 + 10000);
+    assert!(points[3].shortfall);
+    let points = view!(vault.get_projection(110, 110, 1)).unwrap_json::<Vec<ProjectionPoint>>();
+    assert_eq!(points.len(), 1);
+
+    println!("*** Bounded range");
+    assert!(view!(vault.get_projection(150, 50, 10)).is_err());
+    assert!(view!(vault.get_projection(50, 150, 0)).is_err());
+    assert!(view!(vault.get_projection(0, 100000, 1)).is_err());
+}
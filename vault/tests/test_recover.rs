@@ -0,0 +1,55 @@
+use near_sdk::json_types::{U128};
+use near_sdk_sim::{
+    call, to_yocto, view,
+};
+use vault::Stats;
+use crate::common::init::*;
+
+pub mod common;
+
+
+#[test]
+fn recover_excess_tokens() {
+    let (root, owner, vault, token) = setup_vault(10000, 50, 10, 10);
+    call!(owner, token.mint(U128(500))).assert_success();
+    call!(
+        owner,
+        token.ft_transfer(vault.valid_account_id(), U128(10500), None),
+        deposit = 1
+    ).assert_success();
+    let user1 = root.create_user("user1".to_string(), to_yocto("10"));
+    call!(owner, vault.add_account(user1.valid_account_id(), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"))
+    .assert_success();
+    call!(user1, token.storage_deposit(None, None), deposit = to_yocto("1")).assert_success();
+
+    println!("*** Go beyond round 10, user1 claims");
+    assert!(root.borrow_runtime_mut().produce_blocks(150).is_ok());
+    call!(user1, vault.claim()).assert_success();
+    assert_eq!(balance_of(&token, &vault.account_id()), 10300);
+
+    println!("*** Only owner recovers");
+    let out_come = call!(user1, vault.recover_excess(token.valid_account_id(), user1.valid_account_id()));
+    assert!(!out_come.is_ok());
+
+    println!("*** Owner recovers excess of managed token");
+    call!(owner, vault.recover_excess(token.valid_account_id(), owner.valid_account_id())).assert_success();
+    assert_eq!(balance_of(&token, &owner.account_id()), 500);
+    assert_eq!(balance_of(&token, &vault.account_id()), 9800);
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.claimed_balance.0, 200);
+    assert_eq!(vault_stats.in_flight_balance.0, 0);
+
+    println!("*** Nothing more to recover");
+    let out_come = call!(owner, vault.recover_excess(token.valid_account_id(), owner.valid_account_id()));
+    out_come.assert_success();
+    assert_eq!(out_come.unwrap_json::<bool>(), false);
+    assert_eq!(balance_of(&token, &vault.account_id()), 9800);
+
+    println!("*** Whole balance of foreign token is recovered");
+    let other = test_token(&root, "other_token".to_string(), vec!["vault".to_string(), owner.account_id()]);
+    call!(owner, other.mint(U128(300))).assert_success();
+    call!(owner, other.ft_transfer(vault.valid_account_id(), U128(300), None), deposit = 1).assert_success();
+    call!(owner, vault.recover_excess(other.valid_account_id(), owner.valid_account_id())).assert_success();
+    assert_eq!(balance_of(&other, &owner.account_id()), 300);
+    assert_eq!(balance_of(&other, &vault.account_id()), 0);
+}
@@ -0,0 +1,68 @@
+use near_sdk::json_types::{U128};
+use near_sdk::serde_json::Value;
+use near_sdk_sim::{
+    call, to_yocto, view,
+};
+use vault::Stats;
+use crate::common::init::*;
+
+pub mod common;
+
+
+#[test]
+fn claim_with_registration() {
+    let (root, owner, vault, token) = setup_vault(10000, 50, 10, 10);
+    call!(
+        owner,
+        token.ft_transfer(vault.valid_account_id(), U128(10000), None),
+        deposit = 1
+    ).assert_success();
+    let bounds = view!(token.storage_balance_bounds()).unwrap_json::<Value>();
+    let fee: u128 = bounds["min"].as_str().unwrap().parse().unwrap();
+
+    let user1 = root.create_user("user1".to_string(), to_yocto("10"));
+    let user2 = root.create_user("user2".to_string(), to_yocto("10"));
+    call!(owner, vault.add_account(user1.valid_account_id(), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"))
+    .assert_success();
+    call!(owner, vault.add_account(user2.valid_account_id(), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"))
+    .assert_success();
+
+    println!("*** Go beyond round 10, all released");
+    assert!(root.borrow_runtime_mut().produce_blocks(150).is_ok());
+
+    println!("*** User1 claim without deposit and reserve fails as before");
+    let out_come = call!(user1, vault.claim(), deposit = 0);
+    out_come.assert_success();
+    let ex_status = format!("{:?}", out_come.promise_errors()[0].as_ref().unwrap().status());
+    assert!(ex_status.contains("The account user1 is not registered"));
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.claimed_balance.0, 0);
+    assert_eq!(vault_stats.liquid_balance.0, 10000);
+    assert_eq!(vault_stats.unclaimed_balance.0, 400);
+
+    println!("*** User1 claim with attached deposit");
+    call!(user1, vault.claim(), deposit = to_yocto("0.01")).assert_success();
+    assert_eq!(balance_of(&token, &user1.account_id()), 200);
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.claimed_balance.0, 200);
+    assert_eq!(vault_stats.liquid_balance.0, 9800);
+    assert_eq!(vault_stats.unclaimed_balance.0, 200);
+    assert_eq!(vault_stats.registration_reserve.0, 0);
+
+    println!("*** User2 claim with registration reserve");
+    call!(owner, vault.registration_reserve_deposit(), deposit = to_yocto("1")).assert_success();
+    call!(user2, vault.claim(), deposit = 0).assert_success();
+    assert_eq!(balance_of(&token, &user2.account_id()), 200);
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.claimed_balance.0, 400);
+    assert_eq!(vault_stats.liquid_balance.0, 9600);
+    assert_eq!(vault_stats.unclaimed_balance.0, 0);
+    assert_eq!(vault_stats.registration_reserve.0, to_yocto("1") - fee);
+
+    println!("*** Only owner can withdraw reserve");
+    let out_come = call!(user2, vault.registration_reserve_withdraw(U128(1)), deposit = 0);
+    assert!(!out_come.is_ok());
+    call!(owner, vault.registration_reserve_withdraw(U128(to_yocto("1") - fee)), deposit = 0).assert_success();
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.registration_reserve.0, 0);
+}
@@ -0,0 +1,88 @@
+use near_sdk::json_types::{U128};
+use near_sdk_sim::{
+    call, to_yocto, view,
+};
+use vault::{AccountOutput, Stats};
+use crate::common::init::*;
+
+pub mod common;
+
+
+#[test]
+fn renounce_grant() {
+    let (root, owner, vault, token) = setup_vault(10000, 50, 10, 10);
+    call!(
+        owner,
+        token.ft_transfer(vault.valid_account_id(), U128(10000), None),
+        deposit = 1
+    ).assert_success();
+    let user1 = root.create_user("user1".to_string(), to_yocto("10"));
+    let user2 = root.create_user("user2".to_string(), to_yocto("10"));
+    let user3 = root.create_user("user3".to_string(), to_yocto("10"));
+    call!(owner, vault.add_account(user1.valid_account_id(), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"))
+    .assert_success();
+    call!(owner, vault.add_account(user2.valid_account_id(), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"))
+    .assert_success();
+    call!(owner, vault.add_account(user3.valid_account_id(), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"))
+    .assert_success();
+    call!(user1, token.storage_deposit(None, None), deposit = to_yocto("1")).assert_success();
+    call!(user2, token.storage_deposit(None, None), deposit = to_yocto("1")).assert_success();
+    assert!(root.borrow_runtime_mut().produce_blocks(100).is_ok());
+
+    println!("*** Only the beneficiary renounces");
+    let out_come = call!(owner, vault.renounce(user1.valid_account_id(), true));
+    assert!(format!("{:?}", out_come.status()).contains("ERR_NOT_ALLOWED"));
+    let out_come = call!(user1, vault.renounce(user2.valid_account_id(), true));
+    assert!(!out_come.is_ok());
+
+    println!("*** Renounce with claim, vested part is sent");
+    let user_info = view!(vault.get_account(user1.valid_account_id())).unwrap_json::<AccountOutput>();
+    let vested = user_info.unclaimed_amount.0;
+    assert!(vested > 0);
+    let out_come = call!(user1, vault.renounce(user1.valid_account_id(), true));
+    out_come.assert_success();
+    assert!(out_come.unwrap_json::<bool>());
+    assert!(view!(vault.get_account(user1.valid_account_id())).unwrap_json_value().is_null());
+    assert_eq!(balance_of(&token, &user1.account_id()), vested);
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.claimed_balance.0, vested);
+    assert_eq!(vault_stats.in_flight_balance.0, 0);
+
+    println!("*** Renounce without claim, everything is forfeited");
+    let user_info = view!(vault.get_account(user2.valid_account_id())).unwrap_json::<AccountOutput>();
+    assert!(user_info.unclaimed_amount.0 > 0);
+    let out_come = call!(user2, vault.renounce(user2.valid_account_id(), false));
+    out_come.assert_success();
+    assert!(out_come
+        .logs()
+        .iter()
+        .any(|log| log.starts_with(r#"EVENT_JSON:{"standard":"vault","version":"1.0.0","event":"renounce","data":[{"account_id":"user2""#)
+            && log.ends_with(r#""claimed_amount":"0"}]}"#)));
+    assert!(out_come.unwrap_json::<bool>());
+    assert!(view!(vault.get_account(user2.valid_account_id())).unwrap_json_value().is_null());
+    assert_eq!(balance_of(&token, &user2.account_id()), 0);
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    let user_info = view!(vault.get_account(user3.valid_account_id())).unwrap_json::<AccountOutput>();
+    assert_eq!(vault_stats.claimed_balance.0, vested);
+    assert_eq!(vault_stats.unclaimed_balance.0, user_info.unclaimed_amount.0);
+    assert!(view!(vault.check_invariants()).unwrap_json::<Vec<String>>().is_empty());
+
+    println!("*** Failed claim keeps the account, ended, to claim again");
+    let out_come = call!(user3, vault.renounce(user3.valid_account_id(), true));
+    assert!(!out_come.unwrap_json::<bool>());
+    let user_info = view!(vault.get_account(user3.valid_account_id())).unwrap_json::<AccountOutput>();
+    let vested = user_info.unclaimed_amount.0;
+    assert!(vested > 0);
+    assert_eq!(balance_of(&token, &user3.account_id()), 0);
+
+    println!("*** Attached deposit registers, then the account is removed");
+    let out_come = call!(user3, vault.renounce(user3.valid_account_id(), true), deposit = to_yocto("1"));
+    assert!(out_come.unwrap_json::<bool>());
+    assert!(view!(vault.get_account(user3.valid_account_id())).unwrap_json_value().is_null());
+    assert_eq!(balance_of(&token, &user3.account_id()), vested);
+    assert!(view!(vault.check_invariants()).unwrap_json::<Vec<String>>().is_empty());
+
+    println!("*** Forfeited balance is liquid for the owner");
+    let liquid = view!(vault.get_stats()).unwrap_json::<Stats>().liquid_balance.0;
+    call!(owner, vault.payment(owner.valid_account_id(), U128(liquid))).assert_success();
+}
@@ -0,0 +1,56 @@
+use near_sdk::json_types::{U128};
+use near_sdk::serde_json::Value;
+use near_sdk_sim::{
+    call, to_yocto, view,
+};
+use vault::Stats;
+use crate::common::init::*;
+
+pub mod common;
+
+
+#[test]
+fn storage_staking() {
+    let (root, owner, vault, _token) = setup_vault(10000, 50, 10, 10);
+    let user1 = root.create_user("user1".to_string(), to_yocto("10"));
+    let user2 = root.create_user("user2".to_string(), to_yocto("10"));
+
+    println!("*** Add account without deposit and empty pool fails");
+    let out_come = call!(owner, vault.add_account(user1.valid_account_id(), 50, 10, 10, U128(20), None), deposit = 0);
+    assert!(!out_come.is_ok());
+    let ex_status = format!("{:?}", out_come.promise_errors()[0].as_ref().unwrap().status());
+    assert!(ex_status.contains("ERR_INSUFFICIENT_STORAGE"));
+
+    println!("*** Add account with attached deposit, extra goes to pool");
+    call!(owner, vault.add_account(user1.valid_account_id(), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"))
+    .assert_success();
+    let storage = view!(vault.storage_balance_of(user1.valid_account_id())).unwrap_json::<Value>();
+    let staked: u128 = storage["total"].as_str().unwrap().parse().unwrap();
+    assert!(staked > 0);
+    assert_eq!(storage["available"].as_str().unwrap(), "0");
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.storage_pool_balance.0, to_yocto("0.01") - staked);
+
+    println!("*** Add account drawing from pool");
+    call!(owner, vault.storage_pool_deposit(), deposit = to_yocto("1")).assert_success();
+    call!(owner, vault.add_account(user2.valid_account_id(), 50, 10, 10, U128(20), None), deposit = 0)
+    .assert_success();
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.storage_pool_balance.0, to_yocto("1.01") - 2 * staked);
+
+    println!("*** Remove account refunds storage into pool");
+    call!(owner, vault.remove_account(user2.valid_account_id()), deposit = 0).assert_success();
+    let storage = view!(vault.storage_balance_of(user2.valid_account_id())).unwrap_json::<Value>();
+    assert!(storage.is_null());
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.storage_pool_balance.0, to_yocto("1.01") - staked);
+
+    println!("*** Only owner can withdraw from pool");
+    let out_come = call!(user1, vault.storage_pool_withdraw(U128(to_yocto("1"))), deposit = 0);
+    assert!(!out_come.is_ok());
+    let out_come = call!(owner, vault.storage_pool_withdraw(U128(to_yocto("2"))), deposit = 0);
+    assert!(!out_come.is_ok());
+    call!(owner, vault.storage_pool_withdraw(U128(to_yocto("1"))), deposit = 0).assert_success();
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.storage_pool_balance.0, to_yocto("0.01") - staked);
+}
@@ -0,0 +1,63 @@
+use near_sdk::json_types::{U128};
+use near_sdk_sim::{
+    call, to_yocto, view,
+};
+use vault::{AccountOutput, Stats};
+use crate::common::init::*;
+
+pub mod common;
+
+
+#[test]
+fn terminate_and_collect() {
+    let (root, owner, vault, token) = setup_vault(10000, 50, 10, 10);
+    call!(
+        owner,
+        token.ft_transfer(vault.valid_account_id(), U128(10000), None),
+        deposit = 1
+    ).assert_success();
+    let user1 = root.create_user("user1".to_string(), to_yocto("10"));
+    let user2 = root.create_user("user2".to_string(), to_yocto("10"));
+    call!(owner, vault.add_account(user1.valid_account_id(), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"))
+    .assert_success();
+    call!(owner, vault.add_account(user2.valid_account_id(), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"))
+    .assert_success();
+    call!(user1, token.storage_deposit(None, None), deposit = to_yocto("1")).assert_success();
+
+    println!("*** Only owner terminates");
+    let out_come = call!(user1, vault.terminate_account(user1.valid_account_id(), 80), deposit = to_yocto("0.01"));
+    assert!(format!("{:?}", out_come.status()).contains("ERR_NOT_ALLOWED"));
+
+    println!("*** Vesting stops at end timestamp");
+    let out_come = call!(owner, vault.terminate_account(user1.valid_account_id(), 80), deposit = to_yocto("0.01"));
+    out_come.assert_success();
+    assert!(!out_come.unwrap_json::<bool>());
+    assert!(out_come.logs().contains(
+        &r#"EVENT_JSON:{"standard":"vault","version":"1.0.0","event":"terminate","data":[{"account_id":"user1","end_timestamp":80,"forfeited_amount":"140"}]}"#.to_string()
+    ));
+    let user_info = view!(vault.get_account(user1.valid_account_id())).unwrap_json::<AccountOutput>();
+    assert_eq!(user_info.end_timestamp, Some(80));
+    assert_eq!(user_info.release_rounds, 3);
+    assert_eq!(user_info.forfeited_balance.0, 140);
+    let out_come = call!(owner, vault.terminate_account(user1.valid_account_id(), 90), deposit = to_yocto("0.01"));
+    assert!(format!("{:?}", out_come.status()).contains("ERR_ALREADY_TERMINATED"));
+
+    assert!(root.borrow_runtime_mut().produce_blocks(200).is_ok());
+    let user_info = view!(vault.get_account(user1.valid_account_id())).unwrap_json::<AccountOutput>();
+    assert_eq!(user_info.unclaimed_amount.0, 60);
+
+    println!("*** Claiming the rest removes the account");
+    call!(user1, vault.claim()).assert_success();
+    assert_eq!(balance_of(&token, &user1.account_id()), 60);
+    assert!(view!(vault.get_account(user1.valid_account_id())).unwrap_json_value().is_null());
+    assert!(view!(vault.storage_balance_of(user1.valid_account_id())).unwrap_json_value().is_null());
+
+    println!("*** Ending before start removes it right away");
+    let out_come = call!(owner, vault.terminate_account(user2.valid_account_id(), 40), deposit = to_yocto("0.01"));
+    assert!(out_come.unwrap_json::<bool>());
+    assert!(view!(vault.get_account(user2.valid_account_id())).unwrap_json_value().is_null());
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.claimed_balance.0, 60);
+    assert_eq!(vault_stats.unclaimed_balance.0, 0);
+    assert!(view!(vault.check_invariants()).unwrap_json::<Vec<String>>().is_empty());
+}
@@ -0,0 +1,86 @@
+use near_sdk::json_types::{U128};
+use near_sdk_sim::{
+    call, to_yocto, view,
+};
+use vault::Stats;
+use crate::common::init::*;
+
+pub mod common;
+
+
+#[test]
+fn top_up_schedule() {
+    let (root, owner, vault, token) = setup_vault(10000, 50, 10, 10);
+    call!(owner, token.mint(U128(2000))).assert_success();
+    call!(
+        owner,
+        token.ft_transfer(vault.valid_account_id(), U128(10000), None),
+        deposit = 1
+    ).assert_success();
+
+    println!("*** Go beyond round 5");
+    assert!(root.borrow_runtime_mut().produce_blocks(100).is_ok());
+
+    println!("*** Top up over remaining rounds through transfer call");
+    call!(
+        owner,
+        token.ft_transfer_call(vault.valid_account_id(), U128(1000), None, "{\"TopUp\": {\"extend_rounds\": 0}}".to_string()),
+        deposit = 1
+    ).assert_success();
+    assert_eq!(balance_of(&token, &vault.account_id()), 11000);
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.total_balance.0, 11000);
+    assert_eq!(vault_stats.release_rounds, 10);
+    assert_eq!(vault_stats.tranches.len(), 2);
+    let head = &vault_stats.tranches[0];
+    let tail = &vault_stats.tranches[1];
+    // split at the round when top-up happened
+    let cur_round = head.release_rounds;
+    assert!(cur_round > 0 && cur_round < 10);
+    assert_eq!(head.total_balance.0, 1000 * cur_round as u128);
+    assert_eq!(tail.start_timestamp, 50 + 10 * cur_round);
+    assert_eq!(tail.release_rounds, 10 - cur_round);
+    assert_eq!(tail.total_balance.0, 11000 - 1000 * cur_round as u128);
+
+    println!("*** Only owner tops up, others are refunded");
+    call!(root, token.storage_deposit(None, None), deposit = to_yocto("1")).assert_success();
+    call!(owner, token.ft_transfer(root.valid_account_id(), U128(100), None), deposit = 1).assert_success();
+    call!(
+        root,
+        token.ft_transfer_call(vault.valid_account_id(), U128(100), None, "{\"TopUp\": {\"extend_rounds\": 0}}".to_string()),
+        deposit = 1
+    ).assert_success();
+    assert_eq!(balance_of(&token, &vault.account_id()), 11000);
+    assert_eq!(balance_of(&token, &root.account_id()), 100);
+
+    println!("*** Top up not covered by balance fails");
+    let out_come = call!(owner, vault.top_up(U128(500), 5));
+    assert_eq!(out_come.unwrap_json::<bool>(), false);
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.total_balance.0, 11000);
+    assert_eq!(vault_stats.in_flight_balance.0, 0);
+
+    println!("*** Append a segment from transferred tokens");
+    call!(owner, token.ft_transfer(vault.valid_account_id(), U128(500), None), deposit = 1).assert_success();
+    let out_come = call!(owner, vault.top_up(U128(500), 5));
+    assert_eq!(out_come.unwrap_json::<bool>(), true);
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.total_balance.0, 11500);
+    assert_eq!(vault_stats.release_rounds, 15);
+    let appended = &vault_stats.tranches[2];
+    assert_eq!(appended.start_timestamp, 150);
+    assert_eq!(appended.release_interval, 10);
+    assert_eq!(appended.release_rounds, 5);
+    assert_eq!(appended.total_balance.0, 500);
+    assert_eq!(appended.current_round, 0);
+
+    println!("*** Go beyond round 15, all released");
+    assert!(root.borrow_runtime_mut().produce_blocks(150).is_ok());
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.locked_balance.0, 0);
+    assert_eq!(vault_stats.liquid_balance.0, 11500);
+
+    println!("*** Spreading over remaining rounds needs rounds left");
+    let out_come = call!(owner, vault.top_up(U128(100), 0));
+    assert!(!out_come.is_ok());
+}
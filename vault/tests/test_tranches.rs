@@ -0,0 +1,49 @@
+use near_sdk::json_types::{U128};
+use near_sdk_sim::{
+    call, view,
+};
+use vault::{Stats, Tranche};
+use crate::common::init::*;
+
+pub mod common;
+
+
+#[test]
+fn sequential_tranches() {
+    // 1000 over 5 rounds of 10s from 50, then 6000 over 3 rounds of 20s from 100
+    let (root, owner, vault, token) = setup_vault_with_tranches(vec![
+        Tranche { start_timestamp: 50, release_interval: 10, release_rounds: 5, total_balance: U128(1000), release_day: None },
+        Tranche { start_timestamp: 100, release_interval: 20, release_rounds: 3, total_balance: U128(6000), release_day: None },
+    ]);
+    call!(
+        owner,
+        token.ft_transfer(vault.valid_account_id(), U128(7000), None),
+        deposit = 1
+    ).assert_success();
+
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.total_balance.0, 7000);
+    assert_eq!(vault_stats.start_timestamp, 50);
+    assert_eq!(vault_stats.release_rounds, 8);
+    assert_eq!(vault_stats.tranches.len(), 2);
+
+    println!("*** Go into the second tranche");
+    assert!(root.borrow_runtime_mut().produce_blocks(110).is_ok());
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    let first = &vault_stats.tranches[0];
+    let second = &vault_stats.tranches[1];
+    assert_eq!(first.current_round, 5);
+    assert_eq!(first.unlocked_balance.0, 1000);
+    assert_eq!(first.locked_balance.0, 0);
+    assert!(second.current_round < 3);
+    assert_eq!(second.unlocked_balance.0, 2000 * second.current_round as u128);
+    assert_eq!(vault_stats.current_round, 5 + second.current_round);
+    assert_eq!(vault_stats.locked_balance.0, 6000 - second.unlocked_balance.0);
+
+    println!("*** Go beyond all tranches");
+    assert!(root.borrow_runtime_mut().produce_blocks(100).is_ok());
+    let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
+    assert_eq!(vault_stats.locked_balance.0, 0);
+    assert_eq!(vault_stats.liquid_balance.0, 7000);
+    assert!(vault_stats.current_round >= 8);
+}
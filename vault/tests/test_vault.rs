@@ -17,7 +17,7 @@ fn core_logic() {
     // height 26, 
     let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
     // println!("Vault stats: {:#?}", vault_stats);
-    assert_eq!(vault_stats.version, "0.3.1".to_string());
+    assert_eq!(vault_stats.version, "0.4.0".to_string());
     assert_eq!(vault_stats.owner_id, owner.account_id());
     assert_eq!(vault_stats.token_account_id, token.account_id());
     assert_eq!(vault_stats.total_balance.0, 10000);
@@ -46,7 +46,7 @@ fn core_logic() {
     let user1 = root.create_user("user1".to_string(), to_yocto("10"));
     call!(user1, token.storage_deposit(None, None), deposit = to_yocto("1"))
     .assert_success();
-    call!(owner, vault.add_account(user1.valid_account_id(), 50, 10, 10, U128(20)), deposit = 0)
+    call!(owner, vault.add_account(user1.valid_account_id(), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"))
     .assert_success();
     println!("block env ----> height: {}, ts: {}", root.borrow_runtime().current_block().block_height, root.borrow_runtime().current_block().block_timestamp);
     let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
@@ -64,7 +64,7 @@ fn core_logic() {
 
     println!("*** Add user2 without storage_deposit to token");
     let user2 = root.create_user("user2".to_string(), to_yocto("10"));
-    call!(owner, vault.add_account(user2.valid_account_id(), 50, 10, 10, U128(20)), deposit = 0)
+    call!(owner, vault.add_account(user2.valid_account_id(), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"))
     .assert_success();
     println!("block env ----> height: {}, ts: {}", root.borrow_runtime().current_block().block_height, root.borrow_runtime().current_block().block_timestamp);
     let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
@@ -126,7 +126,7 @@ fn core_logic() {
     assert!(!out_come.is_ok());
     let ex_status = format!("{:?}", out_come.promise_errors()[0].as_ref().unwrap().status());
     // println!("ex_status: {}", ex_status);
-    assert!(ex_status.contains("The payment amount beyonds liquidity"));
+    assert!(ex_status.contains("ERR_PAYMENT_BEYOND_LIQUIDITY"));
     println!("block env ----> height: {}, ts: {}", root.borrow_runtime().current_block().block_height, root.borrow_runtime().current_block().block_timestamp);
     let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
     assert_stats(&vault_stats, 9, 1200, 1000, 7800, 160);
@@ -146,9 +146,9 @@ fn remove_user() {
     let user2 = root.create_user("user2".to_string(), to_yocto("10"));
     call!(user1, token.storage_deposit(None, None), deposit = to_yocto("1")).assert_success();
     call!(user2, token.storage_deposit(None, None), deposit = to_yocto("1")).assert_success();
-    call!(owner, vault.add_account(user1.valid_account_id(), 50, 10, 10, U128(20)), deposit = 0)
+    call!(owner, vault.add_account(user1.valid_account_id(), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"))
     .assert_success();
-    call!(owner, vault.add_account(user2.valid_account_id(), 50, 10, 10, U128(20)), deposit = 0)
+    call!(owner, vault.add_account(user2.valid_account_id(), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01"))
     .assert_success();
 
     assert!(root.borrow_runtime_mut().produce_blocks(36).is_ok());
@@ -187,7 +187,7 @@ fn remove_user() {
     let out_come = call!(user2, vault.claim(), deposit = 0);
     assert!(!out_come.is_ok());
     let ex_status = format!("{:?}", out_come.promise_errors()[0].as_ref().unwrap().status());
-    assert!(ex_status.contains("Account not exist in this contract"));
+    assert!(ex_status.contains("ERR_ACCOUNT_NOT_EXIST"));
 }
 
 #[test]
@@ -206,7 +206,7 @@ fn life_cycle() {
     let user1 = root.create_user("user1".to_string(), to_yocto("10"));
     call!(user1, token.storage_deposit(None, None), deposit = to_yocto("1"))
     .assert_success();
-    call!(owner, vault.add_account(user1.valid_account_id(), 50, 10, 10, U128(2)), deposit = 0)
+    call!(owner, vault.add_account(user1.valid_account_id(), 50, 10, 10, U128(2), None), deposit = to_yocto("0.01"))
     .assert_success();
     let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
     assert_stats(&vault_stats, 4, 0, 60, 40, 8);
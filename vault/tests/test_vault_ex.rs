@@ -16,7 +16,7 @@ fn three_employees() {
     
     let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
     // println!("Vault stats: {:#?}", vault_stats);
-    assert_eq!(vault_stats.version, "0.3.1".to_string());
+    assert_eq!(vault_stats.version, "0.4.0".to_string());
     assert_eq!(vault_stats.owner_id, owner.account_id());
     assert_eq!(vault_stats.token_account_id, token.account_id());
     assert_eq!(vault_stats.total_balance.0, 10000);
@@ -48,7 +48,7 @@ fn three_employees() {
     println!("*** Add user1");
     let user1 = root.create_user("user1".to_string(), to_yocto("10"));
     call!(user1, token.storage_deposit(None, None), deposit = to_yocto("1")).assert_success();
-    call!(owner, vault.add_account(user1.valid_account_id(), 50, 10, 10, U128(20))).assert_success();
+    call!(owner, vault.add_account(user1.valid_account_id(), 50, 10, 10, U128(20), None), deposit = to_yocto("0.01")).assert_success();
     println!("block env ----> height: {}", root.borrow_runtime().current_block().block_height);
     let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
     assert_stats(&vault_stats, 3, 0, 7000, 3000, 60);
@@ -56,7 +56,7 @@ fn three_employees() {
     println!("*** Add user2");
     let user2 = root.create_user("user2".to_string(), to_yocto("10"));
     call!(user2, token.storage_deposit(None, None), deposit = to_yocto("1")).assert_success();
-    call!(owner, vault.add_account(user2.valid_account_id(), 55, 10, 10, U128(10))).assert_success();
+    call!(owner, vault.add_account(user2.valid_account_id(), 55, 10, 10, U128(10), None), deposit = to_yocto("0.01")).assert_success();
     println!("block env ----> height: {}", root.borrow_runtime().current_block().block_height);
     let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
     assert_stats(&vault_stats, 4, 0, 6000, 4000, 110);
@@ -64,7 +64,7 @@ fn three_employees() {
     println!("*** Add user3");
     let user3 = root.create_user("user3".to_string(), to_yocto("10"));
     call!(user3, token.storage_deposit(None, None), deposit = to_yocto("1")).assert_success();
-    call!(owner, vault.add_account(user3.valid_account_id(), 60, 10, 10, U128(10))).assert_success();
+    call!(owner, vault.add_account(user3.valid_account_id(), 60, 10, 10, U128(10), None), deposit = to_yocto("0.01")).assert_success();
     println!("block env ----> height: {}", root.borrow_runtime().current_block().block_height);
     let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
     assert_stats(&vault_stats, 5, 0, 5000, 5000, 180);
@@ -115,7 +115,7 @@ fn three_employees() {
     let out_come = call!(owner, vault.payment(user3.valid_account_id(), U128(6800)), deposit = 0);
     assert!(!out_come.is_ok());
     let ex_status = format!("{:?}", out_come.promise_errors()[0].as_ref().unwrap().status());
-    assert!(ex_status.contains("The payment amount beyonds liquidity"));
+    assert!(ex_status.contains("ERR_PAYMENT_BEYOND_LIQUIDITY"));
     println!("block env ----> height: {}", root.borrow_runtime().current_block().block_height);
     let vault_stats = view!(vault.get_stats()).unwrap_json::<Stats>();
     assert_stats(&vault_stats, 7, 140, 3000, 6860, 130);